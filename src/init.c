@@ -44,8 +44,8 @@ SEXP savvy_pgltc_count_scanlines__impl(SEXP c_arg__bytes) {
     return handle_result(res);
 }
 
-SEXP savvy_pgltc_random_copy__impl(SEXP c_arg__bytes, SEXP c_arg__times) {
-    SEXP res = savvy_pgltc_random_copy__ffi(c_arg__bytes, c_arg__times);
+SEXP savvy_pgltc_random_copy__impl(SEXP c_arg__bytes, SEXP c_arg__times, SEXP c_arg__seed) {
+    SEXP res = savvy_pgltc_random_copy__ffi(c_arg__bytes, c_arg__times, c_arg__seed);
     return handle_result(res);
 }
 
@@ -63,7 +63,7 @@ SEXP savvy_pgltc_transpose__impl(SEXP c_arg__bytes, SEXP c_arg__src, SEXP c_arg_
 static const R_CallMethodDef CallEntries[] = {
     {"savvy_pgltc_apply_filter__impl", (DL_FUNC) &savvy_pgltc_apply_filter__impl, 4},
     {"savvy_pgltc_count_scanlines__impl", (DL_FUNC) &savvy_pgltc_count_scanlines__impl, 1},
-    {"savvy_pgltc_random_copy__impl", (DL_FUNC) &savvy_pgltc_random_copy__impl, 2},
+    {"savvy_pgltc_random_copy__impl", (DL_FUNC) &savvy_pgltc_random_copy__impl, 3},
     {"savvy_pgltc_remove_filter__impl", (DL_FUNC) &savvy_pgltc_remove_filter__impl, 3},
     {"savvy_pgltc_transpose__impl", (DL_FUNC) &savvy_pgltc_transpose__impl, 4},
     {NULL, NULL, 0}
@@ -1,6 +1,6 @@
 SEXP savvy_pgltc_apply_filter__ffi(SEXP c_arg__bytes, SEXP c_arg__filter_type, SEXP c_arg__from, SEXP c_arg__lines);
 SEXP savvy_pgltc_count_scanlines__ffi(SEXP c_arg__bytes);
-SEXP savvy_pgltc_random_copy__ffi(SEXP c_arg__bytes, SEXP c_arg__times);
+SEXP savvy_pgltc_random_copy__ffi(SEXP c_arg__bytes, SEXP c_arg__times, SEXP c_arg__seed);
 SEXP savvy_pgltc_remove_filter__ffi(SEXP c_arg__bytes, SEXP c_arg__from, SEXP c_arg__lines);
 SEXP savvy_pgltc_transpose__ffi(SEXP c_arg__bytes, SEXP c_arg__src, SEXP c_arg__dst, SEXP c_arg__lines);
 
@@ -1,17 +1,23 @@
 pub use crate::operation::Transpose;
 use crate::operation::{Encode, Scan};
-use crate::png::Png;
-pub use crate::png::{FilterType, ScanLine};
+use crate::png::{Png, PngError};
+pub use crate::png::{ByteClass, BytePattern, ChecksumPolicy, ColorType, CompressedGlitch, DeflateBackend, Dithering, EncodeOptions, FilterHeuristic, FilterPlan, FilterType, HuffmanGlitch, HuffmanTable, IdatSplit, InvalidFilterPolicy, LineScope, Pixel, QuantizeMethod, RandomCopyOptions, ReconstructedView, ScanLine, Strategy, Token, TokenGlitch, ZlibHeader};
+pub use crate::recipe::{Operation, Recipe};
+use anyhow::Context;
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::Path;
 use std::vec;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use savvy::{savvy, savvy_err};
 
 mod operation;
 mod png;
+mod recipe;
 
 /// PngGlitch is a crate to create a glitched PNG image.
 /// Please refer to ["The Art of PNG glitch"](https://ucnv.github.io/pnglitch/) for the description about what glitched PNG is.
@@ -37,6 +43,8 @@ mod png;
 ///
 pub struct PngGlitch {
     png: Png,
+    operations: RefCell<Vec<Operation>>,
+    rng: ChaCha8Rng,
 }
 
 impl PngGlitch {
@@ -77,10 +85,94 @@ impl PngGlitch {
     /// ```
     pub fn new(buffer: Vec<u8>) -> anyhow::Result<PngGlitch> {
         let png = Png::try_from(&buffer as &[u8])?;
-        Ok(PngGlitch { png })
+        Ok(PngGlitch::from_png(png))
+    }
+
+    /// The method creates a PngGlitch object from pixel data, e.g. generated or decoded by other tools.
+    /// The scan lines are left unfiltered, and an indexed-color image gets a palette of evenly spaced gray levels.
+    /// It fails when the bit depth is not allowed for the color type or the size of the pixel data does not match the image size.
+    ///
+    /// # Example
+    ///
+    /// The following example creates a 256x256 grayscale gradient.
+    ///
+    /// ```
+    /// use pnglitchr::{ColorType, PngGlitch};
+    ///
+    /// let pixels: Vec<u8> = (0..256 * 256).map(|index| (index % 256) as u8).collect();
+    /// let png_glitch = PngGlitch::from_pixels(256, 256, ColorType::GrayScale, 8, &pixels).expect("The pixel data should fill the image");
+    /// png_glitch.save("./gradient.png").expect("The PNG file should be successfully saved");
+    /// ```
+    pub fn from_pixels(width: u32, height: u32, color_type: ColorType, bit_depth: u8, pixels: &[u8]) -> anyhow::Result<PngGlitch> {
+        let png = Png::from_pixels(width, height, color_type, bit_depth, pixels)?;
+        Ok(PngGlitch::from_png(png))
+    }
+
+    /// The method seeds the random generator used by the random glitches, e.g. [PngGlitch::random_copy].
+    /// The same seed gives byte-identical results across runs and platforms.
+    /// Without a seed, the random generator is seeded from the entropy of the operating system.
+    /// The `seed` parameter is the seed of the random generator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{PngGlitch, RandomCopyOptions};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png")
+    ///     .expect("The PNG file should be successfully parsed")
+    ///     .with_seed(42);
+    /// png_glitch.random_copy(10, &RandomCopyOptions::default());
+    /// png_glitch.save("./etc/seeded.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn with_seed(mut self, seed: u64) -> PngGlitch {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// The method splits the image into grayscale images, one per channel, e.g. red, green, blue and alpha.
+    /// Each channel can be glitched independently, and [PngGlitch::merge_channels] recombines them.
+    /// An indexed-color image is split into the channels of its palette colors at 8 bits.
+    /// The channel images start with empty operation logs.
+    ///
+    /// # Example
+    ///
+    /// The following example transposes the scan lines of the red channel only.
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let mut channels = png_glitch.split_channels().expect("The image should be split");
+    /// channels[0].transpose(2, 5, 10);
+    /// let merged = PngGlitch::merge_channels(&channels).expect("The channels should be merged");
+    /// merged.save("./etc/red-shifted.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn split_channels(&self) -> anyhow::Result<Vec<PngGlitch>> {
+        let channels = self.png.split_channels()?;
+        Ok(channels
+            .into_iter()
+            .map(PngGlitch::from_png)
+            .collect())
+    }
+
+    /// The method creates a PngGlitch object from grayscale images, each of which becomes a channel.
+    /// One, two, three and four images make a grayscale, grayscale with alpha, truecolor and truecolor with alpha image respectively.
+    /// It fails when the images are not grayscale or differ in size or bit depth.
+    /// The `channels` parameter is the grayscale images, in the order the channels are stored.
+    pub fn merge_channels(channels: &[PngGlitch]) -> anyhow::Result<PngGlitch> {
+        let channels: Vec<&Png> = channels.iter().map(|channel| &channel.png).collect();
+        let png = Png::merge_channels(&channels)?;
+        Ok(PngGlitch::from_png(png))
+    }
+
+    fn from_png(png: Png) -> PngGlitch {
+        PngGlitch {
+            png,
+            operations: RefCell::new(vec![]),
+            rng: ChaCha8Rng::from_entropy(),
+        }
     }
 
     /// The method returns a list of [scan line](https://www.w3.org/TR/2003/REC-PNG-20031110/#4Concepts.EncodingScanlineAbs%22). in the given PNG file.
+    /// Since the returned scan lines can be modified, the call is recorded as an operation which cannot be replayed.
     ///
     /// # Example
     ///
@@ -102,12 +194,16 @@ impl PngGlitch {
     /// }
     /// ```
     pub fn scan_lines(&self) -> Vec<ScanLine> {
+        self.record(Operation::Opaque {
+            method: "scan_lines".to_string(),
+        });
         self.png.scan_lines()
     }
 
     /// The method takes the specified number of ScanLine objects at most.
     /// The maximum number of ScanLines is specified as `lines` parameter.
     /// The `from` parameter specifies the index of first ScanLine.
+    /// Like [PngGlitch::scan_lines], the call is recorded as an operation which cannot be replayed.
     ///
     /// # Example
     /// ```
@@ -117,6 +213,9 @@ impl PngGlitch {
     /// let scan_liens = png_glitch.scan_lines_from(5, 10);
     /// ```
     pub fn scan_lines_from(&self, from: u32, lines: u32) -> Vec<ScanLine> {
+        self.record(Operation::Opaque {
+            method: "scan_lines_from".to_string(),
+        });
         self.png.scan_lines_from(from as usize, lines as usize)
     }
 
@@ -142,10 +241,14 @@ impl PngGlitch {
     where
         F: FnMut(&mut ScanLine),
     {
+        self.record(Operation::Opaque {
+            method: "foreach_scanline".to_string(),
+        });
         self.png.foreach_scanline(modifier)
     }
 
     /// The method saves the glitched image as a PNG file to the given path.
+    /// It is the same as [PngGlitch::save_with_options] with the default options.
     ///
     /// # Example
     ///
@@ -161,6 +264,7 @@ impl PngGlitch {
     }
 
     /// The method encodes the glitched image as a PNG data and write the encoded data to the given buffer.
+    /// It is the same as [PngGlitch::encode_with_options] with the default options.
     ///
     /// # Example
     ///
@@ -180,6 +284,280 @@ impl PngGlitch {
         Ok(())
     }
 
+    /// The method saves the glitched image as a PNG file to the given path with the given encode options.
+    ///
+    /// # Example
+    ///
+    /// The following example saves `./etc/sample00.png` as `./glitched.png` compressed as small as possible.
+    /// ```
+    /// use pnglitchr::{EncodeOptions, PngGlitch, Strategy};
+    ///
+    /// let png_glitch = PngGlitch::open("etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let options = EncodeOptions::new(Strategy::Best);
+    /// png_glitch.save_with_options("./glitched.png", &options).expect("The glitched PNG data should be saved to the given path");
+    /// ```
+    pub fn save_with_options(&self, path: impl AsRef<Path>, options: &EncodeOptions) -> anyhow::Result<()> {
+        let options = self.embedding_recipe(options, None)?;
+        self.png.save_with_options(path, &options)
+    }
+
+    /// The method encodes the glitched image as a PNG data with the given encode options and write the encoded data to the given buffer.
+    ///
+    /// # Example
+    ///
+    /// The following example compresses the image data with miniz_oxide at level 3 and splits it into 8 KiB IDAT chunks.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{DeflateBackend, EncodeOptions, IdatSplit, PngGlitch, Strategy};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let options = EncodeOptions {
+    ///     level: Some(3),
+    ///     strategy: Strategy::Default,
+    ///     backend: Some(DeflateBackend::Miniz),
+    ///     idat_split: IdatSplit::Fixed(8192),
+    ///     ..EncodeOptions::default()
+    /// };
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_options(&mut encoded_data, &options).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// ```
+    pub fn encode_with_options(&self, buffer: &mut Vec<u8>, options: &EncodeOptions) -> anyhow::Result<()> {
+        let options = self.embedding_recipe(options, None)?;
+        self.png.encode_with_options(buffer, &options)
+    }
+
+    /// The method encodes the glitched image as a PNG data and write the encoded data to the given buffer,
+    /// glitching the compressed image data with the given modifier.
+    /// The modifier is called with the deflate bytes, i.e. the zlib stream without its header and Adler-32 trailer,
+    /// after the [CompressedGlitch] operations in the options are applied and before the CRCs of IDAT chunks are computed.
+    ///
+    /// # Example
+    ///
+    /// The following example corrupts a random byte of the compressed data and then zeroes the 100th byte.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{CompressedGlitch, EncodeOptions, PngGlitch, Recipe};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let options = EncodeOptions {
+    ///     compressed_glitches: vec![CompressedGlitch::RandomReplace { times: 1, seed: 42 }],
+    ///     ..EncodeOptions::default()
+    /// };
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_modifier(&mut encoded_data, &options, |deflate_bytes| {
+    ///     if let Some(byte) = deflate_bytes.get_mut(100) {
+    ///         *byte = 0;
+    ///     }
+    /// }).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// ```
+    pub fn encode_with_modifier<F>(&self, buffer: &mut Vec<u8>, options: &EncodeOptions, modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut Vec<u8>),
+    {
+        let options = self.embedding_recipe(options, Some("encode_with_modifier"))?;
+        self.png.encode_with_modifier(buffer, &options, modifier)
+    }
+
+    /// The method encodes the glitched image as a PNG data and write the encoded data to the given buffer,
+    /// glitching the LZ77 tokens of the compressed image data with the given modifier.
+    /// The modifier is called with the literals and back-references of the deflate stream
+    /// after the [TokenGlitch] operations in the options are applied.
+    /// The tokens are then encoded again with valid Huffman codes, and lengths and distances out of range are fixed,
+    /// so decoders decode the result to completion.
+    ///
+    /// # Example
+    ///
+    /// The following example doubles the distance of every back-reference.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{EncodeOptions, PngGlitch, Token};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_token_modifier(&mut encoded_data, &EncodeOptions::default(), |tokens| {
+    ///     for token in tokens.iter_mut() {
+    ///         if let Token::Match { length, distance } = *token {
+    ///             *token = Token::Match { length, distance: distance.saturating_mul(2) };
+    ///         }
+    ///     }
+    /// }).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// ```
+    pub fn encode_with_token_modifier<F>(&self, buffer: &mut Vec<u8>, options: &EncodeOptions, modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut Vec<Token>),
+    {
+        let options = self.embedding_recipe(options, Some("encode_with_token_modifier"))?;
+        self.png.encode_with_token_modifier(buffer, &options, modifier)
+    }
+
+    /// The method encodes the glitched image as a PNG data and write the encoded data to the given buffer,
+    /// glitching the Huffman tables of the dynamic deflate blocks with the given modifier.
+    /// The modifier is called with the index and the [HuffmanTable] of each dynamic block
+    /// after the [HuffmanGlitch] operations in the options are applied.
+    /// The blocks are written again with the glitched tables while their compressed payload is kept bit by bit,
+    /// so decoders mis-decode the symbols and the errors cascade through the rest of the image.
+    ///
+    /// # Example
+    ///
+    /// The following example swaps the code lengths of the bytes 0 and 255 in every dynamic block.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{EncodeOptions, PngGlitch, Strategy};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_huffman_modifier(&mut encoded_data, &EncodeOptions::new(Strategy::Best), |_, table| {
+    ///     table.literals.swap(0, 255);
+    /// }).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// ```
+    pub fn encode_with_huffman_modifier<F>(&self, buffer: &mut Vec<u8>, options: &EncodeOptions, modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(usize, &mut HuffmanTable),
+    {
+        let options = self.embedding_recipe(options, Some("encode_with_huffman_modifier"))?;
+        self.png.encode_with_huffman_modifier(buffer, &options, modifier)
+    }
+
+    /// The method returns a copy of the decoded image data, i.e. all scan lines including their filter type bytes.
+    ///
+    /// # Example
+    ///
+    /// The following example compresses ./etc/none.png against the image data of ./etc/paeth.png as a preset dictionary.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{EncodeOptions, PngGlitch, ZlibHeader};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// let another = PngGlitch::open("./etc/paeth.png").expect("The PNG file should be successfully parsed");
+    /// let options = EncodeOptions {
+    ///     zlib_header: ZlibHeader {
+    ///         dictionary: Some(another.decoded_data()),
+    ///         ..ZlibHeader::default()
+    ///     },
+    ///     ..EncodeOptions::default()
+    /// };
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_options(&mut encoded_data, &options).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// ```
+    pub fn decoded_data(&self) -> Vec<u8> {
+        self.png.decoded_data()
+    }
+
+    /// The method returns the pixel at the given position, or `None` when it is out of the image.
+    /// The samples are the values stored in the scan line at the bit depth of the image,
+    /// so they are raw filtered values unless the filter type of the scan line is None.
+    /// Use [PngGlitch::to_rgba8] or [PngGlitch::to_rgba16] to get the colors a decoder shows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{Pixel, PngGlitch};
+    /// let png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// if let Some(Pixel::Rgba(r, g, b, a)) = png_glitch.pixel(10, 20) {
+    ///     println!("({}, {}, {}, {})", r, g, b, a);
+    /// }
+    /// ```
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Pixel> {
+        self.png.pixel(x, y)
+    }
+
+    /// The method returns a read-only view of the scan lines, showing the pixel values a decoder reconstructs from them.
+    /// The filters are removed on the fly, so the image data stays untouched
+    /// and it can be decided from the real pixel values what to glitch.
+    /// The `from` parameter is the index of the first scan line in the view.
+    /// The `lines` parameter is the number of scan lines in the view.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let mut png_glitch = PngGlitch::open("./etc/paeth.png").expect("The PNG file should be successfully parsed");
+    /// let view = png_glitch.reconstructed_view(0, 16);
+    /// let bright_lines: Vec<u32> = view
+    ///     .iter()
+    ///     .zip(view.lines())
+    ///     .filter(|(line, _)| line.iter().all(|value| *value > 128))
+    ///     .map(|(_, y)| y as u32)
+    ///     .collect();
+    /// for y in bright_lines {
+    ///     png_glitch.transpose(y, y + 1, 1);
+    /// }
+    /// ```
+    pub fn reconstructed_view(&self, from: usize, lines: usize) -> ReconstructedView {
+        self.png.reconstructed_view(from, lines)
+    }
+
+    /// The method decodes the image into 8-bit RGBA pixels, in row-major order, i.e. `width * height * 4` bytes.
+    /// The filters are removed on a copy of the image data, so the image itself is left as is.
+    /// Samples of any bit depth are scaled to 8 bits, palettes are expanded, and the transparency in the tRNS chunk is applied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let pixels = png_glitch.to_rgba8();
+    /// assert_eq!(pixels.len(), (png_glitch.width() * png_glitch.height() * 4) as usize);
+    /// ```
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.png.to_rgba8()
+    }
+
+    /// The method decodes the image into 16-bit RGBA pixels, in row-major order, i.e. `width * height * 4` samples.
+    /// The filters are removed on a copy of the image data, so the image itself is left as is.
+    /// Samples of any bit depth are scaled to 16 bits, palettes are expanded, and the transparency in the tRNS chunk is applied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let pixels = png_glitch.to_rgba16();
+    /// assert_eq!(pixels.len(), (png_glitch.width() * png_glitch.height() * 4) as usize);
+    /// ```
+    pub fn to_rgba16(&self) -> Vec<u16> {
+        self.png.to_rgba16()
+    }
+
+    /// The method returns the offset in the encoded file where a byte of a scan line is written
+    /// when the image is encoded with the options using [Strategy::Stored].
+    /// The chunks written before the image data, including the texts and the recipe in the options, are taken into account.
+    /// Glitching the byte at the offset in the encoded file modifies exactly the specified byte of the scan line.
+    /// It returns `None` when the scan line or the byte does not exist, or when the options do not write
+    /// stored blocks with fdeflate as they are, e.g. with a level, a preset dictionary or token glitches.
+    ///
+    /// # Example
+    ///
+    /// The following example corrupts the 11th byte of the scan line #5 directly in the encoded data.
+    ///
+    /// ```
+    /// # use std::env;
+    /// # env::set_current_dir(env::var("CARGO_MANIFEST_DIR").unwrap_or(".".to_string())).expect("");
+    /// use pnglitchr::{EncodeOptions, PngGlitch, Strategy};
+    ///
+    /// let png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let options = EncodeOptions::new(Strategy::Stored);
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_options(&mut encoded_data, &options).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    /// if let Some(offset) = png_glitch.stored_offset(5, 10, &options) {
+    ///     encoded_data[offset] = 0;
+    /// }
+    /// ```
+    pub fn stored_offset(&self, line: u32, index: usize, options: &EncodeOptions) -> Option<usize> {
+        let options = self.embedding_recipe(options, None).ok()?;
+        self.png.stored_offset(line as usize, index, &options)
+    }
+
     /// The method returns the width of the loaded PNG file
     ///
     /// # Example
@@ -216,6 +594,16 @@ impl PngGlitch {
         self.png.height()
     }
 
+    /// The method returns the color type of the loaded PNG file
+    pub fn color_type(&self) -> ColorType {
+        self.png.color_type()
+    }
+
+    /// The method returns the bit depth of the loaded PNG file
+    pub fn bit_depth(&self) -> u8 {
+        self.png.bit_depth()
+    }
+
     /// The method copies the lines starting from src to dest
     ///
     /// # Example
@@ -226,6 +614,7 @@ impl PngGlitch {
     /// let width = png_glitch.transpose(2, 5, 10);
     /// ```
     pub fn transpose(&mut self, src: u32, dst: u32, lines: u32) {
+        self.record(Operation::Transpose { src, dst, lines });
         self.png.transpose(src as usize, dst as usize, lines)
     }
 
@@ -240,6 +629,7 @@ impl PngGlitch {
     /// png_glitch.save("./etc/removed-all.png").expect("The PNG file should be successfully saved")
     /// ```
     pub fn remove_filter(&mut self) {
+        self.record(Operation::RemoveFilter { from: 0, lines: self.height() });
         self.png.remove_filter();
     }
 
@@ -254,6 +644,7 @@ impl PngGlitch {
     /// png_glitch.save("./etc/removed-partial.png").expect("The PNG file should be successfully saved")
     /// ```
     pub fn remove_filter_from(&mut self, from: u32, lines: u32) {
+        self.record(Operation::RemoveFilter { from, lines });
         self.png.remove_filter_from(from, lines);
     }
 
@@ -268,6 +659,7 @@ impl PngGlitch {
     /// png_glitch.save("./etc/filter-all.png").expect("The PNG file should be successfully saved")
     /// ```
     pub fn apply_filter(&mut self, filter: FilterType) {
+        self.record(Operation::ApplyFilter { filter_type: filter, from: 0, lines: self.height() });
         self.png.apply_filter(filter);
     }
 
@@ -282,8 +674,605 @@ impl PngGlitch {
     /// png_glitch.save("./etc/filter-partial.png").expect("The PNG file should be successfully saved")
     /// ```
     pub fn apply_filter_from(&mut self, filter_type: FilterType, from: u32, lines: u32) {
+        self.record(Operation::ApplyFilter { filter_type, from, lines });
         self.png.apply_filter_from(filter_type, from, lines);
     }
+
+    /// The method converts the filter of a scan line to the given filter type, keeping its pixels.
+    /// Unlike [PngGlitch::apply_filter_from], which expects unfiltered scan lines,
+    /// the scan line is reconstructed from its current filter and then filtered with the new one,
+    /// so the image looks the same while the filtered data changes.
+    /// Glitches made afterwards spread differently depending on the filter, e.g. Paeth and Up carry them downward.
+    /// See [PngGlitch::graft_filter] for the opposite semantics.
+    /// The `line` parameter is the index of the scan line.
+    /// The `filter_type` parameter is the new filter type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{FilterType, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.convert_filter(10, FilterType::Paeth); // The image looks the same.
+    /// png_glitch.graft_filter(10, FilterType::Up); // The scan line #10 and the following ones are glitched.
+    /// png_glitch.save("./etc/grafted.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn convert_filter(&mut self, line: u32, filter_type: FilterType) {
+        self.convert_filter_from(filter_type, line, 1);
+    }
+
+    /// The method converts the filter of the scan lines in specified region to the given filter type, keeping their pixels.
+    /// See [PngGlitch::convert_filter] for the details.
+    /// The `filter_type` parameter is the new filter type.
+    /// The `from` parameter is the index of the first scan line.
+    /// The `lines` parameter is the number of scan lines.
+    pub fn convert_filter_from(&mut self, filter_type: FilterType, from: u32, lines: u32) {
+        self.record(Operation::ConvertFilter { filter_type, from, lines });
+        self.png.convert_filter_from(filter_type, from, lines);
+    }
+
+    /// The method grafts the given filter type on a scan line, i.e. overwrites only its filter type byte
+    /// as [ScanLine::set_filter_type] does.
+    /// The filtered data is left as it is and decoded with the new filter, which glitches the scan line,
+    /// and through the Up, Average and Paeth filters, the following scan lines.
+    /// See [PngGlitch::convert_filter] for the opposite semantics.
+    /// The `line` parameter is the index of the scan line.
+    /// The `filter_type` parameter is the new filter type.
+    pub fn graft_filter(&mut self, line: u32, filter_type: FilterType) {
+        self.graft_filter_from(filter_type, line, 1);
+    }
+
+    /// The method grafts the given filter type on the scan lines in specified region.
+    /// See [PngGlitch::graft_filter] for the details.
+    /// The `filter_type` parameter is the new filter type.
+    /// The `from` parameter is the index of the first scan line.
+    /// The `lines` parameter is the number of scan lines.
+    pub fn graft_filter_from(&mut self, filter_type: FilterType, from: u32, lines: u32) {
+        self.record(Operation::GraftFilter { filter_type, from, lines });
+        self.png.graft_filter_from(filter_type, from, lines);
+    }
+
+    /// The method applies to each scan line the filter chosen by the given heuristic.
+    /// The scan lines are expected to be unfiltered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{FilterHeuristic, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.apply_adaptive_filter(FilterHeuristic::MinSumOfAbsoluteDifferences);
+    /// png_glitch.save("./etc/filter-adaptive.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn apply_adaptive_filter(&mut self, heuristic: FilterHeuristic) {
+        self.apply_adaptive_filter_from(heuristic, 0, self.height());
+    }
+
+    /// The method applies to each scan line in specified region the filter chosen by the given heuristic.
+    /// The scan lines and the one preceding the region are expected to be unfiltered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{FilterHeuristic, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.apply_adaptive_filter_from(FilterHeuristic::MinEntropy, 5, 3); // Filter the scan line #5, #6, and #7.
+    /// png_glitch.save("./etc/filter-adaptive-partial.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn apply_adaptive_filter_from(&mut self, heuristic: FilterHeuristic, from: u32, lines: u32) {
+        self.record(Operation::ApplyAdaptiveFilter { heuristic, from, lines });
+        self.png.apply_adaptive_filter_from(heuristic, from, lines);
+    }
+
+    /// The method applies to each scan line the filter assigned by the given plan.
+    /// The scan lines are reconstructed first, so every scan line is filtered against its reconstructed predecessor
+    /// and the pixels stay the same whatever filters the scan lines had.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{FilterPlan, FilterType, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.apply_filter_plan(&FilterPlan::Repeat(vec![FilterType::Sub, FilterType::Up, FilterType::Paeth]));
+    /// png_glitch.save("./etc/filter-plan.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn apply_filter_plan(&mut self, plan: &FilterPlan) {
+        let filters = (0..plan.lines(self.height()) as usize)
+            .map(|index| plan.filter_type(index).unwrap_or(FilterType::None))
+            .collect();
+        self.record(Operation::ApplyFilterPlan { filters });
+        self.png.apply_filter_plan(plan);
+    }
+
+    /// The method converts all the pixels to the given color type and bit depth, leaving the scan lines unfiltered.
+    /// The same glitch gives quite different results depending on the pixel layout.
+    /// Colors are converted to gray by their luma, and alpha is dropped for color types without alpha.
+    /// Converting to indexed color fails when the image has more colors than the palette of the bit depth can hold;
+    /// use [PngGlitch::quantize] for such images.
+    ///
+    /// # Example
+    ///
+    /// The following example converts the image to 16-bit grayscale and glitches it.
+    ///
+    /// ```
+    /// use pnglitchr::{ColorType, FilterType, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.convert(ColorType::GrayScale, 16).expect("The image should be converted");
+    /// png_glitch.apply_filter(FilterType::Paeth);
+    /// png_glitch.transpose(2, 5, 10);
+    /// png_glitch.save("./etc/converted.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn convert(&mut self, color_type: ColorType, bit_depth: u8) -> anyhow::Result<()> {
+        self.png.convert(color_type, bit_depth)?;
+        self.record(Operation::Convert { color_type, bit_depth });
+        Ok(())
+    }
+
+    /// The method reinterprets the decoded data under another IHDR, keeping the data itself as it is.
+    /// A width one pixel wider than the original shears the image diagonally,
+    /// and reading RGB as RGBA or 8-bit as 16-bit mixes up the samples.
+    /// The data is truncated or padded with zeros to fit the new geometry,
+    /// and the filter type bytes which are not valid after re-slicing are handled under the `policy`.
+    /// The `width` parameter is the new width.
+    /// The `height` parameter is the new height.
+    /// The `color_type` parameter is the new color type.
+    /// The `bit_depth` parameter is the new bit depth.
+    /// The `policy` parameter is the policy for invalid filter type bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{InvalidFilterPolicy, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let (width, height) = (png_glitch.width(), png_glitch.height());
+    /// png_glitch.remove_filter();
+    /// png_glitch
+    ///     .reinterpret(width + 1, height, png_glitch.color_type(), png_glitch.bit_depth(), InvalidFilterPolicy::default())
+    ///     .expect("The image should be reinterpreted");
+    /// png_glitch.save("./etc/sheared.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn reinterpret(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        bit_depth: u8,
+        policy: InvalidFilterPolicy,
+    ) -> anyhow::Result<()> {
+        self.png.reinterpret(width, height, color_type, bit_depth, policy)?;
+        self.record(Operation::Reinterpret {
+            width,
+            height,
+            color_type,
+            bit_depth,
+            policy,
+        });
+        Ok(())
+    }
+
+    /// The method copies randomly chosen scan lines over other randomly chosen ones, which is the glitch `glitch_replace` in R.
+    /// The random generator of the object is used, which can be seeded with [PngGlitch::with_seed].
+    /// The `times` parameter is the number of the copies.
+    /// The `options` parameter is the options of the copies, e.g. the number of the consecutive scan lines copied at once.
+    ///
+    /// # Example
+    ///
+    /// The following example copies 4 consecutive scan lines 10 times within the top 100 scan lines,
+    /// leaving the filter types of the destination lines as they are.
+    ///
+    /// ```
+    /// use pnglitchr::{PngGlitch, RandomCopyOptions};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let options = RandomCopyOptions {
+    ///     lines: 4,
+    ///     source_band: Some(0..100),
+    ///     destination_band: Some(0..100),
+    ///     with_filter: false,
+    /// };
+    /// png_glitch.random_copy(10, &options);
+    /// png_glitch.save("./etc/random-copy.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn random_copy(&mut self, times: u32, options: &RandomCopyOptions) {
+        let mut rng = self.rng.clone();
+        self.random_copy_with_rng(times, options, &mut rng);
+        self.rng = rng;
+    }
+
+    /// The method copies randomly chosen scan lines over other randomly chosen ones, using the given random generator.
+    /// The copies are recorded as they are made, so replaying them does not need the random generator.
+    /// The `times` parameter is the number of the copies.
+    /// The `options` parameter is the options of the copies.
+    /// The `rng` parameter is the random generator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{PngGlitch, RandomCopyOptions};
+    /// use rand::SeedableRng;
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    /// png_glitch.random_copy_with_rng(10, &RandomCopyOptions::default(), &mut rng);
+    /// ```
+    pub fn random_copy_with_rng(&mut self, times: u32, options: &RandomCopyOptions, rng: &mut impl Rng) {
+        for _ in 0..times {
+            let Some((src, dst, lines)) = options.choose(self.png.height(), rng) else {
+                return;
+            };
+            self.copy_lines(src, dst, lines, options.with_filter);
+        }
+    }
+
+    /// The method copies scan lines over other scan lines. Unlike [PngGlitch::transpose], the source lines are kept as they are.
+    /// The lines are clamped to the image.
+    /// The `src` parameter is the index of the first scan line to copy.
+    /// The `dst` parameter is the index of the first scan line to overwrite.
+    /// The `lines` parameter is the number of the scan lines to copy.
+    /// The `with_filter` parameter is whether the filter types are copied with the pixel data.
+    pub fn copy_lines(&mut self, src: u32, dst: u32, lines: u32, with_filter: bool) {
+        self.png.copy_lines(src as usize, dst as usize, lines as usize, with_filter);
+        self.record(Operation::CopyLines {
+            src,
+            dst,
+            lines,
+            with_filter,
+        });
+    }
+
+    /// The method replaces the matches of the byte pattern in the filtered data of the scan lines, like `gsub`.
+    /// The pattern and the replacement can differ in length; the rest of each scan line is shifted,
+    /// and truncated or padded with zeros to the size of the scan line.
+    /// Matches do not span scan lines, and the filter type bytes are left as they are.
+    /// It returns the number of the replaced matches.
+    /// The `pattern` parameter is the pattern to search, which supports byte classes. See [BytePattern] for the syntax.
+    /// The `replacement` parameter is the bytes to replace the matches with.
+    /// The `scope` parameter is the scan lines to search.
+    ///
+    /// # Example
+    ///
+    /// The following example replaces each pair of a zero byte and a byte from 0x80 to 0xFF with a byte 0x7F
+    /// in the first 100 scan lines.
+    ///
+    /// ```
+    /// use pnglitchr::{BytePattern, LineScope, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// let pattern = BytePattern::parse(r"\x00[\x80-\xFF]").expect("The pattern should be valid");
+    /// let count = png_glitch.replace_bytes(&pattern, &[0x7F], LineScope::Lines { from: 0, lines: 100 });
+    /// println!("{} matches are replaced", count);
+    /// png_glitch.save("./etc/gsub.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn replace_bytes(&mut self, pattern: &BytePattern, replacement: &[u8], scope: LineScope) -> usize {
+        let count = self.png.replace_bytes(pattern, replacement, scope);
+        self.record(Operation::ReplaceBytes {
+            pattern: pattern.clone(),
+            replacement: replacement.to_vec(),
+            scope,
+        });
+        count
+    }
+
+    /// The method crops the image to the given rectangle.
+    /// The filters are removed before cropping, so the scan lines are left unfiltered.
+    /// The `x` parameter is the horizontal position of the left edge of the rectangle.
+    /// The `y` parameter is the vertical position of the top edge of the rectangle.
+    /// The `width` parameter is the width of the rectangle.
+    /// The `height` parameter is the height of the rectangle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.crop(10, 20, 64, 32).expect("The rectangle should be inside the image");
+    /// png_glitch.save("./etc/cropped.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> anyhow::Result<()> {
+        self.png.crop(x, y, width, height)?;
+        self.record(Operation::Crop { x, y, width, height });
+        Ok(())
+    }
+
+    /// The method pads the image with pixels of the given value, e.g. to bring images to a common size.
+    /// The filters are removed before padding, so the scan lines are left unfiltered.
+    /// The `top` parameter is the number of the rows added above the image.
+    /// The `right` parameter is the number of the columns added to the right of the image.
+    /// The `bottom` parameter is the number of the rows added below the image.
+    /// The `left` parameter is the number of the columns added to the left of the image.
+    /// The `fill` parameter is the pixel to fill the added area with, which should match the color type and the bit depth.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{Pixel, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.pad(8, 8, 8, 8, Pixel::Rgba(0, 0, 0, 255)).expect("The fill pixel should match the image");
+    /// png_glitch.save("./etc/padded.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn pad(&mut self, top: u32, right: u32, bottom: u32, left: u32, fill: Pixel) -> anyhow::Result<()> {
+        self.png.pad(top, right, bottom, left, fill)?;
+        self.record(Operation::Pad {
+            top,
+            right,
+            bottom,
+            left,
+            fill,
+        });
+        Ok(())
+    }
+
+    /// The method resizes the image with the nearest-neighbour interpolation.
+    /// The filters are removed before resizing, so the scan lines are left unfiltered.
+    /// The `width` parameter is the new width.
+    /// The `height` parameter is the new height.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::PngGlitch;
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.resize(128, 128).expect("The size should be positive");
+    /// png_glitch.save("./etc/resized.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        self.png.resize(width, height)?;
+        self.record(Operation::Resize { width, height });
+        Ok(())
+    }
+
+    /// The method rotates the image by 90 degrees clockwise.
+    /// The filters are removed before rotating, so the scan lines are left unfiltered.
+    /// The scan lines run horizontally, so glitching the rotated image and rotating it back gives vertical glitches.
+    ///
+    /// # Example
+    ///
+    /// The following example glitches the image vertically.
+    ///
+    /// ```
+    /// use pnglitchr::{FilterType, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.rotate90().expect("The image should be rotated");
+    /// png_glitch.apply_filter(FilterType::Sub);
+    /// png_glitch.foreach_scanline(|scan_line| scan_line[4] = 0xFF);
+    /// png_glitch.rotate270().expect("The image should be rotated");
+    /// png_glitch.save("./etc/vertical.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn rotate90(&mut self) -> anyhow::Result<()> {
+        self.png.rotate90()?;
+        self.record(Operation::Rotate90);
+        Ok(())
+    }
+
+    /// The method rotates the image by 180 degrees.
+    /// The filters are removed before rotating, so the scan lines are left unfiltered.
+    pub fn rotate180(&mut self) -> anyhow::Result<()> {
+        self.png.rotate180()?;
+        self.record(Operation::Rotate180);
+        Ok(())
+    }
+
+    /// The method rotates the image by 270 degrees clockwise, i.e. 90 degrees counterclockwise.
+    /// The filters are removed before rotating, so the scan lines are left unfiltered.
+    pub fn rotate270(&mut self) -> anyhow::Result<()> {
+        self.png.rotate270()?;
+        self.record(Operation::Rotate270);
+        Ok(())
+    }
+
+    /// The method flips the image horizontally.
+    /// The filters are removed before flipping, so the scan lines are left unfiltered.
+    pub fn flip_horizontal(&mut self) -> anyhow::Result<()> {
+        self.png.flip_horizontal()?;
+        self.record(Operation::FlipHorizontal);
+        Ok(())
+    }
+
+    /// The method flips the image vertically.
+    /// The filters are removed before flipping, so the scan lines are left unfiltered.
+    pub fn flip_vertical(&mut self) -> anyhow::Result<()> {
+        self.png.flip_vertical()?;
+        self.record(Operation::FlipVertical);
+        Ok(())
+    }
+
+    /// The method converts the image to an indexed-color image with a generated palette of at most `max_colors` colors.
+    /// The bit depth is the smallest one whose palette holds `max_colors` colors, and the scan lines are left unfiltered.
+    /// Glitching the palette indices, or the PLTE chunk, gives a look which truecolor images never have.
+    ///
+    /// # Example
+    ///
+    /// The following example reduces the image to 16 colors chosen by k-means clustering with Floyd–Steinberg dithering.
+    ///
+    /// ```
+    /// use pnglitchr::{Dithering, PngGlitch, QuantizeMethod};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.quantize(16, QuantizeMethod::KMeans { iterations: 8 }, Dithering::FloydSteinberg).expect("The image should be quantized");
+    /// png_glitch.save("./etc/quantized.png").expect("The PNG file should be successfully saved")
+    /// ```
+    pub fn quantize(&mut self, max_colors: usize, method: QuantizeMethod, dithering: Dithering) -> anyhow::Result<()> {
+        self.png.quantize(max_colors, method, dithering)?;
+        self.record(Operation::Quantize { max_colors, method, dithering });
+        Ok(())
+    }
+
+    /// The method returns the operations performed so far, in order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{FilterType, Operation, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/none.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.apply_filter_from(FilterType::Sub, 5, 3);
+    /// assert_eq!(png_glitch.operation_log(), vec![Operation::ApplyFilter { filter_type: FilterType::Sub, from: 5, lines: 3 }]);
+    /// ```
+    pub fn operation_log(&self) -> Vec<Operation> {
+        self.operations.borrow().clone()
+    }
+
+    /// The method returns the recipe embedded in the original PNG image, if any.
+    /// Use [Recipe::from_png] to read the recipe of an image whose compressed data is too broken to be parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pnglitchr::{EncodeOptions, PngGlitch};
+    /// let mut png_glitch = PngGlitch::open("./etc/sample00.png").expect("The PNG file should be successfully parsed");
+    /// png_glitch.transpose(2, 5, 10);
+    /// let options = EncodeOptions { embed_recipe: true, ..EncodeOptions::default() };
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_options(&mut encoded_data, &options).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    ///
+    /// let glitched = PngGlitch::new(encoded_data).expect("The glitched PNG data should be successfully parsed");
+    /// let recipe = glitched.embedded_recipe().expect("The recipe should be successfully parsed");
+    /// assert_eq!(recipe.map(|recipe| recipe.operations), Some(png_glitch.operation_log()));
+    /// ```
+    pub fn embedded_recipe(&self) -> anyhow::Result<Option<Recipe>> {
+        self.png
+            .text(Recipe::KEYWORD)
+            .map(|json| Recipe::from_json(&json))
+            .transpose()
+    }
+
+    /// The method applies the operations in the recipe to the original PNG image,
+    /// and returns the image encoded with the options in the recipe.
+    /// It fails when the recipe contains an [Operation::Opaque], which runs user code,
+    /// or uses a preset dictionary, which [PngGlitch::replay_with_dictionary] takes.
+    ///
+    /// # Example
+    ///
+    /// The following example reproduces a glitched image from the original one and the recipe embedded in the glitched image.
+    ///
+    /// ```
+    /// use pnglitchr::{CompressedGlitch, EncodeOptions, PngGlitch, Recipe};
+    /// let original = std::fs::read("./etc/sample00.png").expect("The PNG file should be read");
+    /// let mut png_glitch = PngGlitch::new(original.clone()).expect("The PNG file should be successfully parsed");
+    /// png_glitch.transpose(2, 5, 10);
+    /// let options = EncodeOptions {
+    ///     compressed_glitches: vec![CompressedGlitch::RandomReplace { times: 3, seed: 42 }],
+    ///     embed_recipe: true,
+    ///     ..EncodeOptions::default()
+    /// };
+    /// let mut encoded_data:Vec<u8> = vec![];
+    /// png_glitch.encode_with_options(&mut encoded_data, &options).expect("The glitched PNG data should be written into the encoded_data in PNG format");
+    ///
+    /// let recipe = Recipe::from_png(&encoded_data).expect("The recipe should be parsed").expect("The recipe should be embedded");
+    /// let replayed = PngGlitch::replay(original, &recipe).expect("The recipe should be replayed");
+    /// assert_eq!(replayed, encoded_data);
+    /// ```
+    pub fn replay(original: Vec<u8>, recipe: &Recipe) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(
+            recipe.dictionary_id.is_none(),
+            "The recipe uses a preset dictionary, which should be given to replay_with_dictionary"
+        );
+        PngGlitch::replay_with(original, recipe, None)
+    }
+
+    /// The method replays a recipe whose encode options use a preset dictionary, which the recipe leaves out.
+    /// It fails when the dictionary is not the one the recipe was made with.
+    /// The `original` parameter is the original PNG image.
+    /// The `recipe` parameter is the recipe to replay.
+    /// The `dictionary` parameter is the preset dictionary used to encode the glitched image.
+    pub fn replay_with_dictionary(original: Vec<u8>, recipe: &Recipe, dictionary: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let header = ZlibHeader {
+            dictionary: Some(dictionary.to_vec()),
+            ..ZlibHeader::default()
+        };
+        anyhow::ensure!(
+            recipe.dictionary_id.is_some() && recipe.dictionary_id == header.dictionary_id(),
+            "The dictionary should be the one the recipe was made with"
+        );
+        PngGlitch::replay_with(original, recipe, header.dictionary)
+    }
+
+    fn replay_with(original: Vec<u8>, recipe: &Recipe, dictionary: Option<Vec<u8>>) -> anyhow::Result<Vec<u8>> {
+        let mut png_glitch = PngGlitch::new(original)?;
+        for operation in recipe.operations.iter() {
+            png_glitch.perform(operation)?;
+        }
+        let mut options = recipe.encode_options.clone().unwrap_or_default();
+        options.zlib_header.dictionary = dictionary;
+        let mut buffer = vec![];
+        png_glitch.encode_with_options(&mut buffer, &options)?;
+        Ok(buffer)
+    }
+
+    fn perform(&mut self, operation: &Operation) -> anyhow::Result<()> {
+        match operation {
+            Operation::Transpose { src, dst, lines } => self.transpose(*src, *dst, *lines),
+            Operation::RemoveFilter { from, lines } => self.remove_filter_from(*from, *lines),
+            Operation::ApplyFilter { filter_type, from, lines } => self.apply_filter_from(*filter_type, *from, *lines),
+            Operation::ConvertFilter { filter_type, from, lines } => self.convert_filter_from(*filter_type, *from, *lines),
+            Operation::GraftFilter { filter_type, from, lines } => self.graft_filter_from(*filter_type, *from, *lines),
+            Operation::ApplyAdaptiveFilter { heuristic, from, lines } => {
+                self.apply_adaptive_filter_from(*heuristic, *from, *lines)
+            }
+            Operation::ApplyFilterPlan { filters } => self.apply_filter_plan(&FilterPlan::Explicit(filters.clone())),
+            Operation::Convert { color_type, bit_depth } => self.convert(*color_type, *bit_depth)?,
+            Operation::Quantize { max_colors, method, dithering } => self.quantize(*max_colors, *method, *dithering)?,
+            Operation::Reinterpret {
+                width,
+                height,
+                color_type,
+                bit_depth,
+                policy,
+            } => self.reinterpret(*width, *height, *color_type, *bit_depth, *policy)?,
+            Operation::CopyLines {
+                src,
+                dst,
+                lines,
+                with_filter,
+            } => self.copy_lines(*src, *dst, *lines, *with_filter),
+            Operation::ReplaceBytes {
+                pattern,
+                replacement,
+                scope,
+            } => {
+                self.replace_bytes(pattern, replacement, *scope);
+            }
+            Operation::Crop { x, y, width, height } => self.crop(*x, *y, *width, *height)?,
+            Operation::Pad {
+                top,
+                right,
+                bottom,
+                left,
+                fill,
+            } => self.pad(*top, *right, *bottom, *left, *fill)?,
+            Operation::Resize { width, height } => self.resize(*width, *height)?,
+            Operation::Rotate90 => self.rotate90()?,
+            Operation::Rotate180 => self.rotate180()?,
+            Operation::Rotate270 => self.rotate270()?,
+            Operation::FlipHorizontal => self.flip_horizontal()?,
+            Operation::FlipVertical => self.flip_vertical()?,
+            Operation::Opaque { method } => {
+                return Err(PngError::UnreplayableOperation)
+                    .context(format!("The code given to `{}` is not recorded", method));
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, operation: Operation) {
+        self.operations.borrow_mut().push(operation);
+    }
+
+    /// The method returns the options with the recipe of the operations performed so far added as a text, if requested.
+    /// The `modifier` parameter is the name of the encoding method taking a modifier, which is recorded as an opaque operation.
+    fn embedding_recipe<'a>(&self, options: &'a EncodeOptions, modifier: Option<&str>) -> anyhow::Result<Cow<'a, EncodeOptions>> {
+        if !options.embed_recipe {
+            return Ok(Cow::Borrowed(options));
+        }
+        let mut encode_options = options.clone();
+        encode_options.zlib_header.dictionary = None;
+        let mut recipe = Recipe {
+            operations: self.operation_log(),
+            encode_options: Some(encode_options),
+            dictionary_id: options.zlib_header.dictionary_id(),
+        };
+        if let Some(method) = modifier {
+            recipe.operations.push(Operation::Opaque {
+                method: method.to_string(),
+            });
+        }
+        let mut options = options.clone();
+        options.texts.push((Recipe::KEYWORD.to_string(), recipe.to_json()?));
+        Ok(Cow::Owned(options))
+    }
 }
 
 fn raw_to_png_glitch(raw: savvy::RawSexp) -> anyhow::Result<PngGlitch> {
@@ -295,32 +1284,19 @@ fn raw_to_png_glitch(raw: savvy::RawSexp) -> anyhow::Result<PngGlitch> {
 ///
 /// @param bytes PNG image data
 /// @param times Number of times to copy
+/// @param seed Seed of the random generator
 /// @returns PNG image data
 /// @noRd
 #[savvy]
-fn pgltc_random_copy(bytes: savvy::RawSexp, times: savvy::NumericScalar) -> savvy::Result<savvy::Sexp> {
-    let png = raw_to_png_glitch(bytes).map_err(|_| savvy_err!("Failed to parse PNG data"))?;
-
-    let mut scan_lines = png.scan_lines();
-    let mut rng = rand::thread_rng();
-    let index_range = 0..scan_lines.len();
+fn pgltc_random_copy(bytes: savvy::RawSexp, times: savvy::NumericScalar, seed: Option<savvy::NumericScalar>) -> savvy::Result<savvy::Sexp> {
+    let mut png = raw_to_png_glitch(bytes).map_err(|_| savvy_err!("Failed to parse PNG data"))?;
+    if let Some(seed) = seed {
+        // Negative seeds are as good as positive ones, so the bits of the integer part are used as they are.
+        png = png.with_seed(seed.as_f64() as i64 as u64);
+    }
     let times = times.as_i32()?;
+    png.random_copy(times.max(0) as u32, &RandomCopyOptions::default());
 
-    for _ in 0..times {
-        let src = rng.gen_range(index_range.clone());
-        let dst = rng.gen_range(index_range.clone());
-
-        let src = &mut scan_lines[src];
-        let filter_type = src.filter_type();
-        let mut buffer = vec![];
-        src.read_to_end(&mut buffer)
-            .map_err(|_| savvy_err!("Failed to read scan line"))?;
-
-        let dst = &mut scan_lines[dst];
-        dst.write_all(&buffer)
-            .map_err(|_| savvy_err!("Failed to write scan line"))?;
-        dst.set_filter_type(filter_type);
-    }
     let mut buf = vec![];
     png.encode(&mut buf)
         .map_err(|_| savvy_err!("Failed to encode PNG data"))?;
@@ -414,7 +1390,7 @@ fn pgltc_apply_filter(bytes: savvy::RawSexp, filter_type: savvy::NumericScalar,
 #[savvy]
 fn pgltc_count_scanlines(bytes: savvy::RawSexp) -> savvy::Result<savvy::Sexp> {
     let png = raw_to_png_glitch(bytes).map_err(|_| savvy_err!("Failed to parse PNG data"))?;
-    let n = png.scan_lines().len() as i32;
+    let n = png.height() as i32;
     let out = savvy::OwnedIntegerSexp::try_from_scalar(n)?;
     Ok(out.into())
 }
@@ -0,0 +1,87 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// An enum representing a glitch applied to the deflate bytes after the image data is compressed.
+///
+/// The deflate bytes are the compressed stream without the 2 bytes zlib header and the Adler-32 trailer.
+/// Corrupting them makes the decoder mis-decode everything after the corrupted byte,
+/// which results in smeared and cascading artifacts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressedGlitch {
+    /// Replaces the byte at `index` with `value`.
+    Replace {
+        /// The index of the byte to replace.
+        index: usize,
+        /// The value to write.
+        value: u8,
+    },
+    /// Copies `length` bytes starting at `src` to `dst`.
+    Copy {
+        /// The index of the first byte to copy.
+        src: usize,
+        /// The index where the bytes are pasted.
+        dst: usize,
+        /// The number of bytes to copy.
+        length: usize,
+    },
+    /// Replaces randomly chosen bytes with random values `times` times.
+    RandomReplace {
+        /// The number of bytes to replace.
+        times: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+    /// Copies `length` bytes from a random position to another random position `times` times.
+    RandomCopy {
+        /// The number of copies.
+        times: usize,
+        /// The number of bytes to copy at once.
+        length: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+}
+
+impl CompressedGlitch {
+    /// The method applies the glitch to the deflate bytes.
+    /// Indices out of the deflate bytes are clamped, so the length of the bytes never changes.
+    /// The `data` parameter is the deflate bytes.
+    pub fn apply(&self, data: &mut [u8]) {
+        if data.is_empty() {
+            return;
+        }
+        match *self {
+            Self::Replace { index, value } => {
+                if let Some(byte) = data.get_mut(index) {
+                    *byte = value;
+                }
+            }
+            Self::Copy { src, dst, length } => copy(data, src, dst, length),
+            Self::RandomReplace { times, seed } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for _ in 0..times {
+                    let index = rng.gen_range(0..data.len());
+                    data[index] = rng.gen();
+                }
+            }
+            Self::RandomCopy { times, length, seed } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for _ in 0..times {
+                    let src = rng.gen_range(0..data.len());
+                    let dst = rng.gen_range(0..data.len());
+                    copy(data, src, dst, length);
+                }
+            }
+        }
+    }
+}
+
+fn copy(data: &mut [u8], src: usize, dst: usize, length: usize) {
+    if src >= data.len() || dst >= data.len() {
+        return;
+    }
+    let length = length.min(data.len() - src).min(data.len() - dst);
+    data.copy_within(src..src + length, dst);
+}
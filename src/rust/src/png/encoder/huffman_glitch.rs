@@ -0,0 +1,98 @@
+use crate::png::zlib::{distance_of_symbol, HuffmanTable};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// An enum representing a glitch applied to the Huffman tables of dynamic deflate blocks.
+///
+/// The compressed payload of each block is kept as it is, so the decoder decodes it with the glitched table.
+/// The glitches exchange code lengths between symbols, which keeps every code complete,
+/// so the stream decodes to completion unless a mis-decoded symbol is invalid or refers too far back.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HuffmanGlitch {
+    /// Shuffles the code lengths of the literal symbols, i.e. bytes from 0 to 255, in every block.
+    PermuteLiterals {
+        /// The seed of the random generator.
+        seed: u64,
+    },
+    /// Swaps the code lengths of 2 randomly chosen literal symbols `times` times in every block.
+    SwapLiterals {
+        /// The number of swaps in a block.
+        times: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+    /// Shuffles the code lengths of the distance symbols in every block, among the symbols with the same number of extra bits,
+    /// e.g. distances 1 to 4, 5 to 8, or 9 to 16, so that the rest of the block is decoded as it was.
+    /// Only the symbols whose distances do not exceed the bytes decoded before the first match of the block are shuffled,
+    /// so that every distance refers to decoded data.
+    PermuteDistances {
+        /// The seed of the random generator.
+        seed: u64,
+    },
+}
+
+impl HuffmanGlitch {
+    /// The method applies the glitch to the Huffman tables.
+    /// The `tables` parameter is the list of the tables of dynamic blocks.
+    /// The `histories` parameter is the number of bytes decoded before the first match of each block,
+    /// including the preset dictionary.
+    pub fn apply(&self, tables: &mut [&mut HuffmanTable], histories: &[usize]) {
+        match *self {
+            Self::PermuteLiterals { seed } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for table in tables.iter_mut() {
+                    table.literals[..LITERALS].shuffle(&mut rng);
+                }
+            }
+            Self::SwapLiterals { times, seed } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for table in tables.iter_mut() {
+                    for _ in 0..times {
+                        let a = rng.gen_range(0..LITERALS);
+                        let b = rng.gen_range(0..LITERALS);
+                        table.literals.swap(a, b);
+                    }
+                }
+            }
+            Self::PermuteDistances { seed } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for (table, history) in tables.iter_mut().zip(histories) {
+                    for group in distance_groups(*history) {
+                        // Tables listing fewer symbols, e.g. the single distance of fdeflate, are padded with unused ones.
+                        if table.distances.len() < group.end {
+                            table.distances.resize(group.end, 0);
+                        }
+                        table.distances[group].shuffle(&mut rng);
+                    }
+                }
+            }
+        }
+    }
+}
+
+const LITERALS: usize = 256;
+
+/// The function returns the ranges of the distance symbols with the same number of extra bits,
+/// limited to the symbols whose distances do not exceed the history.
+/// Shuffling the code lengths within such a range keeps the codes of the other symbols
+/// and the number of bits every code reads.
+fn distance_groups(history: usize) -> Vec<Range<usize>> {
+    let mut groups: Vec<(u8, Range<usize>)> = vec![];
+    for symbol in 0.. {
+        let Some((base, extra_bits)) = distance_of_symbol(symbol as u16) else {
+            break;
+        };
+        if base as usize + (1 << extra_bits) - 1 > history {
+            break;
+        }
+        match groups.last_mut() {
+            Some((bits, range)) if *bits == extra_bits => range.end = symbol + 1,
+            _ => groups.push((extra_bits, symbol..symbol + 1)),
+        }
+    }
+    groups.into_iter().map(|(_, range)| range).collect()
+}
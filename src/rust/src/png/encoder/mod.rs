@@ -0,0 +1,502 @@
+pub use compressed_glitch::CompressedGlitch;
+use crate::png::png_error::PngError;
+use crate::png::zlib::{adler32, compress_with_dictionary, header_bytes, BlockType, HuffmanTable, Token, ZlibStream};
+pub use huffman_glitch::HuffmanGlitch;
+pub use token_glitch::TokenGlitch;
+use anyhow::Context;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::io::Cursor;
+
+mod compressed_glitch;
+mod huffman_glitch;
+mod token_glitch;
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressionStrategy, CompressorOxide,
+    TDEFLFlush, TDEFLStatus,
+};
+use miniz_oxide::inflate::core::inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+use miniz_oxide::inflate::core::{decompress, DecompressorOxide};
+use serde::{Deserialize, Serialize};
+
+/// An enum representing the deflate strategy used to compress the image data.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// The fastest strategy.
+    #[default]
+    Fast,
+    /// A balanced strategy between speed and size, equivalent to zlib's level 6.
+    Default,
+    /// The strategy producing the smallest output, equivalent to zlib's level 9.
+    Best,
+    /// The strategy encoding literals only with Huffman coding, without searching matches.
+    HuffmanOnly,
+    /// The strategy searching only matches with distance 1, i.e. run-length encoding.
+    Rle,
+    /// The strategy writing the data as stored deflate blocks without compression.
+    /// With fdeflate, every byte of the image data sits at the offset [stored_offset] returns.
+    /// The only level it accepts is 0, which miniz_oxide writes.
+    Stored,
+}
+
+impl Strategy {
+    fn level(&self) -> u8 {
+        match self {
+            Self::Stored => 0,
+            Self::Fast => 1,
+            Self::Default | Self::HuffmanOnly | Self::Rle => 6,
+            Self::Best => 9,
+        }
+    }
+
+    fn compression_strategy(&self) -> CompressionStrategy {
+        match self {
+            Self::HuffmanOnly => CompressionStrategy::HuffmanOnly,
+            Self::Rle => CompressionStrategy::RLE,
+            _ => CompressionStrategy::Default,
+        }
+    }
+}
+
+/// An enum representing the library used to deflate the image data.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeflateBackend {
+    /// [fdeflate](https://crates.io/crates/fdeflate), a fast encoder supporting only the fast and the stored strategies.
+    Fdeflate,
+    /// [miniz_oxide](https://crates.io/crates/miniz_oxide), which supports every strategy and level.
+    Miniz,
+}
+
+/// An enum representing how the compressed image data is split into IDAT chunks.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdatSplit {
+    /// The whole compressed data is stored in a single IDAT chunk.
+    #[default]
+    Single,
+    /// The compressed data is split into IDAT chunks of the given size in bytes.
+    /// The last chunk may be shorter.
+    Fixed(usize),
+    /// The compressed data is split into IDAT chunks whose sizes are chosen randomly
+    /// from `min` to `max` bytes, both inclusive, with the random generator seeded with `seed`.
+    Random {
+        /// The minimum size of a chunk.
+        min: usize,
+        /// The maximum size of a chunk.
+        max: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+}
+
+impl IdatSplit {
+    /// The method splits the compressed data into the payloads of IDAT chunks.
+    /// The `data` parameter is the compressed data.
+    pub fn split(&self, data: Vec<u8>) -> Vec<Vec<u8>> {
+        if *self == Self::Single {
+            return vec![data];
+        }
+        let mut list = vec![];
+        let mut rest = data.as_slice();
+        for size in self.chunk_sizes(data.len()) {
+            let (chunk, remaining) = rest.split_at(size);
+            list.push(chunk.to_vec());
+            rest = remaining;
+        }
+        list
+    }
+
+    /// The method returns the sizes of IDAT chunks storing compressed data of the given length.
+    /// The `length` parameter is the length of the compressed data.
+    pub fn chunk_sizes(&self, length: usize) -> Vec<usize> {
+        let mut rng = match *self {
+            Self::Random { seed, .. } => Some(ChaCha8Rng::seed_from_u64(seed)),
+            _ => None,
+        };
+        let mut sizes = vec![];
+        let mut rest = length;
+        while rest > 0 || sizes.is_empty() {
+            let size = match (*self, rng.as_mut()) {
+                (Self::Fixed(size), _) => size.max(1),
+                (Self::Random { min, max, .. }, Some(rng)) => {
+                    let min = min.max(1);
+                    rng.gen_range(min..=max.max(min))
+                }
+                _ => rest,
+            };
+            let size = size.min(rest);
+            sizes.push(size);
+            rest -= size;
+        }
+        sizes
+    }
+}
+
+/// An enum representing how a checksum is written when the data it covers may have been glitched.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumPolicy {
+    /// The checksum is recomputed over the data actually written, so strict decoders accept it.
+    Recompute,
+    /// The checksum is left stale, i.e. computed over the data before it was glitched.
+    Stale,
+}
+
+/// A struct representing the fields of the zlib header to override.
+/// The fields left as `None` keep the values the deflate backend writes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZlibHeader {
+    /// The CINFO field from 0 to 7, the base-2 logarithm of the window size minus 8.
+    pub window_size: Option<u8>,
+    /// The FLEVEL field from 0 to 3, hinting how hard the compressor worked.
+    pub level_hint: Option<u8>,
+    /// The preset dictionary, e.g. the image data of another image.
+    /// With a dictionary, the image data is compressed against it by this crate itself regardless of the backend and the strategy,
+    /// and the FDICT flag and the identifier of the dictionary are written in the header.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl ZlibHeader {
+    /// The method returns the identifier of the preset dictionary written in the header, i.e. its Adler-32 checksum, if any.
+    pub fn dictionary_id(&self) -> Option<u32> {
+        self.dictionary.as_deref().map(adler32)
+    }
+}
+
+/// A struct representing the options to encode a PNG image.
+///
+/// The default options compress the image data with fdeflate's fast strategy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncodeOptions {
+    /// The compression level from 0 to 10. `None` means the level implied by the strategy.
+    pub level: Option<u8>,
+    /// The deflate strategy.
+    pub strategy: Strategy,
+    /// The deflate backend. `None` means fdeflate for the fast and the stored strategies without a level,
+    /// and miniz_oxide for others.
+    pub backend: Option<DeflateBackend>,
+    /// The way to split the compressed data into IDAT chunks.
+    pub idat_split: IdatSplit,
+    /// The fields of the zlib header to override.
+    pub zlib_header: ZlibHeader,
+    /// The glitches applied to the LZ77 tokens of the compressed data, in order.
+    pub token_glitches: Vec<TokenGlitch>,
+    /// The glitches applied to the Huffman tables of dynamic deflate blocks, in order.
+    pub huffman_glitches: Vec<HuffmanGlitch>,
+    /// The glitches applied to the deflate bytes after compression, in order.
+    pub compressed_glitches: Vec<CompressedGlitch>,
+    /// The policy for the Adler-32 trailer of the zlib stream. It is stale by default.
+    /// Recomputing it decodes the glitched stream as far as possible and computes the checksum of the decoded data.
+    pub adler32_policy: ChecksumPolicy,
+    /// The policy for the CRCs of all chunks. They are recomputed by default.
+    /// Stale CRCs of IDAT chunks are computed over the compressed data before it was glitched,
+    /// and those of other chunks are kept as they were read.
+    pub crc_policy: ChecksumPolicy,
+    /// The pairs of a keyword and a text written as iTXt chunks.
+    /// They replace the iTXt chunks with the same keywords in the original image.
+    pub texts: Vec<(String, String)>,
+    /// Whether [crate::PngGlitch] embeds the recipe of the operations it has performed in an iTXt chunk.
+    pub embed_recipe: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            level: None,
+            strategy: Strategy::default(),
+            backend: None,
+            idat_split: IdatSplit::default(),
+            zlib_header: ZlibHeader::default(),
+            token_glitches: vec![],
+            huffman_glitches: vec![],
+            compressed_glitches: vec![],
+            adler32_policy: ChecksumPolicy::Stale,
+            crc_policy: ChecksumPolicy::Recompute,
+            texts: vec![],
+            embed_recipe: false,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The method creates options with the given strategy.
+    /// The `strategy` parameter is the deflate strategy.
+    pub fn new(strategy: Strategy) -> EncodeOptions {
+        EncodeOptions {
+            strategy,
+            ..EncodeOptions::default()
+        }
+    }
+
+    /// The method returns whether the image data is written as stored blocks by fdeflate and left there as it is,
+    /// so that every byte of the image data sits at the offset [stored_offset] returns.
+    pub fn keeps_stored_layout(&self) -> bool {
+        self.strategy == Strategy::Stored
+            && self.level.is_none()
+            && self.backend() == DeflateBackend::Fdeflate
+            && self.zlib_header.dictionary.is_none()
+            && self.token_glitches.is_empty()
+    }
+
+    fn backend(&self) -> DeflateBackend {
+        match self.backend {
+            Some(backend) => backend,
+            None if matches!(self.strategy, Strategy::Stored | Strategy::Fast) && self.level.is_none() => {
+                DeflateBackend::Fdeflate
+            }
+            None => DeflateBackend::Miniz,
+        }
+    }
+}
+
+/// The function compresses the given data into a zlib stream according to the options.
+/// The `data` parameter is the data to compress.
+/// The `options` parameter specifies how the data is compressed.
+pub fn deflate(data: &[u8], options: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    let mut stream = match (&options.zlib_header.dictionary, options.backend()) {
+        (Some(dictionary), _) => compress_with_dictionary(data, dictionary),
+        (None, DeflateBackend::Fdeflate) => deflate_with_fdeflate(data, options)?,
+        (None, DeflateBackend::Miniz) => deflate_with_miniz(data, options)?,
+    };
+    rewrite_zlib_header(&mut stream, &options.zlib_header);
+    Ok(stream)
+}
+
+fn rewrite_zlib_header(stream: &mut [u8], header: &ZlibHeader) {
+    if stream.len() < ZLIB_HEADER_SIZE || (header.window_size.is_none() && header.level_hint.is_none()) {
+        return;
+    }
+    let window_size = header.window_size.unwrap_or(stream[0] >> 4).min(7);
+    let level_hint = header.level_hint.unwrap_or(stream[1] >> 6);
+    let has_dictionary = stream[1] & 0x20 != 0;
+    stream[..ZLIB_HEADER_SIZE].copy_from_slice(&header_bytes(window_size, level_hint, has_dictionary));
+}
+
+/// A type alias for a function glitching the LZ77 tokens of compressed data.
+pub type TokenModifier<'a> = &'a mut dyn FnMut(&mut Vec<Token>);
+/// A type alias for a function glitching the Huffman table of a dynamic deflate block, called with the index of the block.
+pub type HuffmanModifier<'a> = &'a mut dyn FnMut(usize, &mut HuffmanTable);
+/// A type alias for a function glitching deflate bytes.
+pub type CompressedModifier<'a> = &'a mut dyn FnMut(&mut Vec<u8>);
+
+/// A struct holding the functions to glitch the compressed image data while it is encoded.
+#[derive(Default)]
+pub struct Modifiers<'a> {
+    /// The function called with the LZ77 tokens of the compressed data.
+    pub tokens: Option<TokenModifier<'a>>,
+    /// The function called with the Huffman table of each dynamic deflate block.
+    pub huffman: Option<HuffmanModifier<'a>>,
+    /// The function called with the deflate bytes.
+    pub compressed: Option<CompressedModifier<'a>>,
+}
+
+/// The function glitches the LZ77 tokens in a zlib stream and encodes them again.
+/// The glitches in the options are applied first, and then the modifier is called.
+/// It does nothing when there are neither glitches nor a modifier.
+/// The `stream` parameter is the zlib stream.
+/// The `options` parameter holds the glitches to apply.
+/// The `modifier` parameter is a function called with the tokens.
+pub fn glitch_tokens(stream: &mut Vec<u8>, options: &EncodeOptions, modifier: Option<TokenModifier>) -> anyhow::Result<()> {
+    if options.token_glitches.is_empty() && modifier.is_none() {
+        return Ok(());
+    }
+    let parsed = ZlibStream::parse(stream).context("Failed to parse the compressed image data.")?;
+    let mut tokens = parsed.tokens();
+    for glitch in options.token_glitches.iter() {
+        glitch.apply(&mut tokens);
+    }
+    if let Some(modifier) = modifier {
+        modifier(&mut tokens);
+    }
+    let dictionary = options.zlib_header.dictionary.as_deref().unwrap_or_default();
+    *stream = crate::png::zlib::encode(&parsed.header, &mut tokens, dictionary);
+    Ok(())
+}
+
+/// The function glitches the Huffman tables of the dynamic blocks in a zlib stream
+/// and writes the blocks again without changing their compressed payload.
+/// The glitches in the options are applied first, and then the modifier is called for each table.
+/// It does nothing when there are neither glitches nor a modifier.
+/// The `stream` parameter is the zlib stream.
+/// The `options` parameter holds the glitches to apply.
+/// The `modifier` parameter is a function called with the index of the block and its table.
+pub fn glitch_huffman(stream: &mut Vec<u8>, options: &EncodeOptions, modifier: Option<HuffmanModifier>) -> anyhow::Result<()> {
+    if options.huffman_glitches.is_empty() && modifier.is_none() {
+        return Ok(());
+    }
+    let mut parsed = ZlibStream::parse(stream).context("Failed to parse the compressed image data.")?;
+    let mut position = options.zlib_header.dictionary.as_ref().map_or(0, Vec::len);
+    let mut histories = vec![];
+    for block in parsed.blocks.iter() {
+        let mut history = None;
+        for token in block.tokens.iter() {
+            match token {
+                Token::Literal(_) => position += 1,
+                Token::Match { length, .. } => {
+                    history.get_or_insert(position);
+                    position += *length as usize;
+                }
+            }
+        }
+        if let BlockType::Dynamic(_) = block.block_type {
+            histories.push(history.unwrap_or(position));
+        }
+    }
+    let mut tables: Vec<(usize, &mut HuffmanTable)> = parsed
+        .blocks
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(index, block)| match &mut block.block_type {
+            BlockType::Dynamic(table) => Some((index, table)),
+            _ => None,
+        })
+        .collect();
+    for glitch in options.huffman_glitches.iter() {
+        let mut list: Vec<&mut HuffmanTable> = tables.iter_mut().map(|(_, table)| &mut **table).collect();
+        glitch.apply(&mut list, &histories);
+    }
+    if let Some(modifier) = modifier {
+        for (index, table) in tables.iter_mut() {
+            modifier(*index, table);
+        }
+    }
+    // The modifier may resize the tables, which the block headers cannot hold as they are.
+    for (_, table) in tables.iter_mut() {
+        table.fit();
+    }
+    *stream = parsed.reemit()?;
+    Ok(())
+}
+
+/// The function glitches the deflate bytes in a zlib stream.
+/// The glitches in the options are applied first, and then the modifier is called.
+/// The zlib header, including the DICTID of a preset dictionary, and the Adler-32 trailer are kept untouched.
+/// The `stream` parameter is the zlib stream.
+/// The `options` parameter holds the glitches to apply.
+/// The `modifier` parameter is a function called with the deflate bytes.
+pub fn glitch_compressed(stream: &mut Vec<u8>, options: &EncodeOptions, modifier: Option<CompressedModifier>) {
+    let header_size = zlib_header_size(stream);
+    if stream.len() < header_size + ADLER32_SIZE {
+        return;
+    }
+    let trailer = stream.split_off(stream.len() - ADLER32_SIZE);
+    let mut body = stream.split_off(header_size);
+    for glitch in options.compressed_glitches.iter() {
+        glitch.apply(&mut body);
+    }
+    if let Some(modifier) = modifier {
+        modifier(&mut body);
+    }
+    stream.extend(body);
+    stream.extend(trailer);
+}
+
+/// The function rewrites the Adler-32 trailer of a zlib stream with the checksum of the data the stream decodes to.
+/// A corrupted stream is decoded as far as possible, and the checksum covers the decoded part.
+/// The `stream` parameter is the zlib stream.
+/// The `capacity` parameter is the maximum number of bytes to decode.
+/// The `dictionary` parameter is the preset dictionary the stream refers to, which is empty without one.
+pub fn recompute_adler32(stream: &mut [u8], capacity: usize, dictionary: &[u8]) {
+    let header_size = zlib_header_size(stream);
+    if stream.len() < header_size + ADLER32_SIZE {
+        return;
+    }
+    // The dictionary precedes the output, so that the back-references into it are resolved.
+    let mut decompressor = DecompressorOxide::new();
+    let mut buffer = dictionary.to_vec();
+    buffer.resize(dictionary.len() + capacity, 0);
+    let body = &stream[header_size..stream.len() - ADLER32_SIZE];
+    let flags = TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+    let (_, _, written) = decompress(&mut decompressor, body, &mut buffer, dictionary.len(), flags);
+
+    let checksum = adler32(&buffer[dictionary.len()..dictionary.len() + written]).to_be_bytes();
+    let length = stream.len();
+    stream[length - ADLER32_SIZE..].copy_from_slice(&checksum);
+}
+
+/// The function returns the offset in a zlib stream of stored blocks where the byte of the image data is written.
+/// The `index` parameter is the index of the byte in the image data.
+pub fn stored_offset(index: usize) -> usize {
+    ZLIB_HEADER_SIZE + (index / STORED_BLOCK_SIZE + 1) * STORED_BLOCK_HEADER_SIZE + index
+}
+
+/// The function returns the size of a zlib stream of stored blocks.
+/// The `length` parameter is the length of the image data.
+pub fn stored_size(length: usize) -> usize {
+    let blocks = std::cmp::max(length.div_ceil(STORED_BLOCK_SIZE), 1);
+    ZLIB_HEADER_SIZE + blocks * STORED_BLOCK_HEADER_SIZE + length + ADLER32_SIZE
+}
+
+/// The function returns the size of the zlib header, including the DICTID when the FDICT bit is set.
+fn zlib_header_size(stream: &[u8]) -> usize {
+    match stream.get(1) {
+        Some(flags) if flags & 0x20 != 0 => ZLIB_HEADER_SIZE + DICTID_SIZE,
+        _ => ZLIB_HEADER_SIZE,
+    }
+}
+
+const ZLIB_HEADER_SIZE: usize = 2;
+const DICTID_SIZE: usize = 4;
+const STORED_BLOCK_HEADER_SIZE: usize = 5;
+const STORED_BLOCK_SIZE: usize = u16::MAX as usize;
+const ADLER32_SIZE: usize = 4;
+
+fn deflate_with_fdeflate(data: &[u8], options: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    if options.level.is_some() {
+        return Err(PngError::UnsupportedCompression)
+            .context("fdeflate does not support compression levels.");
+    }
+    match options.strategy {
+        Strategy::Fast => {
+            let mut encoder = fdeflate::Compressor::new(vec![])?;
+            encoder.write_data(data)?;
+            let buffer = encoder.finish()?;
+            Ok(buffer)
+        }
+        Strategy::Stored => {
+            let mut encoder = fdeflate::StoredOnlyCompressor::new(Cursor::new(vec![]))?;
+            encoder.write_data(data)?;
+            let buffer = encoder.finish()?;
+            Ok(buffer.into_inner())
+        }
+        _ => Err(PngError::UnsupportedCompression)
+            .context("fdeflate supports only the fast and the stored strategies."),
+    }
+}
+
+fn deflate_with_miniz(mut data: &[u8], options: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    if options.strategy == Strategy::Stored && options.level.is_some_and(|level| level != 0) {
+        return Err(PngError::UnsupportedCompression)
+            .context("The stored strategy does not compress the data, so its level should be 0.");
+    }
+    let level = options.level.unwrap_or(options.strategy.level()).min(10);
+    let strategy = options.strategy.compression_strategy();
+    let flags = create_comp_flags_from_zip_params(level as i32, 15, strategy as i32);
+    let mut compressor = CompressorOxide::new(flags);
+
+    let mut buffer = vec![0; std::cmp::max(data.len() / 2, 64)];
+    let mut position = 0;
+    loop {
+        let (status, consumed, written) =
+            compress(&mut compressor, data, &mut buffer[position..], TDEFLFlush::Finish);
+        position += written;
+        match status {
+            TDEFLStatus::Done => {
+                buffer.truncate(position);
+                return Ok(buffer);
+            }
+            TDEFLStatus::Okay => {
+                data = &data[consumed..];
+                if buffer.len() - position < 64 {
+                    buffer.resize(buffer.len() * 2, 0);
+                }
+            }
+            _ => {
+                return Err(PngError::DeflateFailure)
+                    .context("miniz_oxide failed to compress the image data.")
+            }
+        }
+    }
+}
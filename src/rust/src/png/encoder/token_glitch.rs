@@ -0,0 +1,96 @@
+use crate::png::zlib::Token;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// An enum representing a glitch applied to the LZ77 tokens of the compressed image data.
+///
+/// The glitched tokens are encoded again with valid Huffman codes,
+/// so decoders decode the stream to completion while back-references copy unexpected bytes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenGlitch {
+    /// Adds `delta` to the distances of `times` randomly chosen back-references.
+    ShiftDistance {
+        /// The value added to the distance.
+        delta: i32,
+        /// The number of back-references to shift.
+        times: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+    /// Adds `delta` to the lengths of `times` randomly chosen back-references.
+    StretchLength {
+        /// The value added to the length.
+        delta: i32,
+        /// The number of back-references to stretch.
+        times: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+    /// Swaps the values of 2 randomly chosen literals `times` times.
+    SwapLiterals {
+        /// The number of swaps.
+        times: usize,
+        /// The seed of the random generator.
+        seed: u64,
+    },
+}
+
+impl TokenGlitch {
+    /// The method applies the glitch to the tokens.
+    /// Lengths and distances out of range are fixed when the tokens are encoded.
+    /// The `tokens` parameter is the tokens of the compressed image data.
+    pub fn apply(&self, tokens: &mut [Token]) {
+        match *self {
+            Self::ShiftDistance { delta, times, seed } => {
+                update_matches(tokens, times, seed, |length, distance| {
+                    (length, shift(distance, delta))
+                })
+            }
+            Self::StretchLength { delta, times, seed } => {
+                update_matches(tokens, times, seed, |length, distance| {
+                    (shift(length, delta), distance)
+                })
+            }
+            Self::SwapLiterals { times, seed } => {
+                let literals: Vec<usize> = (0..tokens.len())
+                    .filter(|index| matches!(tokens[*index], Token::Literal(_)))
+                    .collect();
+                if literals.is_empty() {
+                    return;
+                }
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                for _ in 0..times {
+                    let a = literals[rng.gen_range(0..literals.len())];
+                    let b = literals[rng.gen_range(0..literals.len())];
+                    tokens.swap(a, b);
+                }
+            }
+        }
+    }
+}
+
+fn shift(value: u16, delta: i32) -> u16 {
+    (value as i32 + delta).clamp(0, u16::MAX as i32) as u16
+}
+
+fn update_matches<F>(tokens: &mut [Token], times: usize, seed: u64, callback: F)
+where
+    F: Fn(u16, u16) -> (u16, u16),
+{
+    let matches: Vec<usize> = (0..tokens.len())
+        .filter(|index| matches!(tokens[*index], Token::Match { .. }))
+        .collect();
+    if matches.is_empty() {
+        return;
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for _ in 0..times {
+        let index = matches[rng.gen_range(0..matches.len())];
+        if let Token::Match { length, distance } = tokens[index] {
+            let (length, distance) = callback(length, distance);
+            tokens[index] = Token::Match { length, distance };
+        }
+    }
+}
@@ -5,8 +5,14 @@ use crate::png::parser::Terminator;
 use crate::png::parser::{Chunk, ChunkType};
 pub use crate::png::scan_line::ScanLine;
 use anyhow::Context;
+use encoder::Modifiers;
+use pixel::{Palette, RgbaDecoder, RgbaEncoder};
+pub use pixel::{Dithering, Pixel, QuantizeMethod};
+pub use encoder::{ChecksumPolicy, CompressedGlitch, DeflateBackend, EncodeOptions, HuffmanGlitch, IdatSplit, Strategy, TokenGlitch, ZlibHeader};
 pub use parser::ColorType;
-pub use scan_line::FilterType;
+pub(crate) use png_error::PngError;
+pub use random_copy_options::RandomCopyOptions;
+pub use scan_line::{ByteClass, BytePattern, FilterHeuristic, FilterPlan, FilterType, InvalidFilterPolicy, LineScope, ReconstructedView};
 use scan_line::MemoryRange;
 use std::cell::RefCell;
 use std::fs::File;
@@ -14,9 +20,14 @@ use std::ops::Range;
 use std::path::Path;
 use std::rc::Rc;
 
+mod encoder;
 mod parser;
+mod pixel;
 mod png_error;
+mod random_copy_options;
 mod scan_line;
+mod zlib;
+pub use zlib::{HuffmanTable, Token};
 
 /// A type alias for a vector of bytes representing decoded PNG data.
 pub type DecodedData = Vec<u8>;
@@ -40,11 +51,115 @@ impl Png {
     /// The method saves the PNG image to a file.
     /// The `path` parameter is the path to the file.
     pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.save_with_options(path, &EncodeOptions::default())
+    }
+
+    /// The method saves the PNG image to a file with the given encode options.
+    /// The `path` parameter is the path to the file.
+    /// The `options` parameter specifies how the image data is compressed.
+    pub fn save_with_options(&self, path: impl AsRef<Path>, options: &EncodeOptions) -> anyhow::Result<()> {
         let mut file = File::create(path)?;
-        self.encode(&mut file)?;
+        self.encode_with_options(&mut file, options)?;
         Ok(())
     }
 
+    /// The method encodes the PNG image with the given encode options and writes it to a buffer.
+    /// The `writer` parameter is a writable buffer.
+    /// The `options` parameter specifies how the image data is compressed.
+    pub fn encode_with_options(&self, writer: impl std::io::Write, options: &EncodeOptions) -> anyhow::Result<()> {
+        self.encode_with_modifiers(writer, options, Modifiers::default())
+    }
+
+    /// The method encodes the PNG image and writes it to a buffer, letting the modifier glitch the compressed data.
+    /// The modifier is called with the deflate bytes after the glitches in the options are applied,
+    /// before the data is split into IDAT chunks and their CRCs are computed.
+    /// The `writer` parameter is a writable buffer.
+    /// The `options` parameter specifies how the image data is compressed.
+    /// The `modifier` parameter is a function to glitch the deflate bytes.
+    pub fn encode_with_modifier<F>(&self, writer: impl std::io::Write, options: &EncodeOptions, mut modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut Vec<u8>),
+    {
+        let modifiers = Modifiers {
+            compressed: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        self.encode_with_modifiers(writer, options, modifiers)
+    }
+
+    /// The method encodes the PNG image and writes it to a buffer, letting the modifier glitch the LZ77 tokens of the compressed data.
+    /// The modifier is called with the tokens after the glitches in the options are applied,
+    /// and then the tokens are encoded again with valid Huffman codes.
+    /// The `writer` parameter is a writable buffer.
+    /// The `options` parameter specifies how the image data is compressed.
+    /// The `modifier` parameter is a function to glitch the tokens.
+    pub fn encode_with_token_modifier<F>(&self, writer: impl std::io::Write, options: &EncodeOptions, mut modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut Vec<Token>),
+    {
+        let modifiers = Modifiers {
+            tokens: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        self.encode_with_modifiers(writer, options, modifiers)
+    }
+
+    /// The method encodes the PNG image and writes it to a buffer, letting the modifier glitch the Huffman tables of the compressed data.
+    /// The modifier is called with the index and the Huffman table of each dynamic deflate block
+    /// after the glitches in the options are applied, and then the blocks are written again with their payload untouched.
+    /// The `writer` parameter is a writable buffer.
+    /// The `options` parameter specifies how the image data is compressed.
+    /// The `modifier` parameter is a function to glitch the Huffman tables.
+    pub fn encode_with_huffman_modifier<F>(&self, writer: impl std::io::Write, options: &EncodeOptions, mut modifier: F) -> anyhow::Result<()>
+    where
+        F: FnMut(usize, &mut HuffmanTable),
+    {
+        let modifiers = Modifiers {
+            huffman: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        self.encode_with_modifiers(writer, options, modifiers)
+    }
+
+    fn encode_with_modifiers(&self, mut writer: impl std::io::Write, options: &EncodeOptions, modifiers: Modifiers) -> anyhow::Result<()> {
+        let encode_chunk = |chunk: &Chunk, writer: &mut dyn std::io::Write| match options.crc_policy {
+            ChecksumPolicy::Recompute => chunk.encode_with_crc(writer, chunk.compute_crc()),
+            ChecksumPolicy::Stale => chunk.encode(writer),
+        };
+        writer.write_all(SIGNATURE)?;
+        encode_chunk(&self.header.inner, &mut writer).context("Failed to encode IHDR")?;
+        for chunk in self.written_misc_chunks(options) {
+            encode_chunk(chunk, &mut writer)?;
+        }
+        for chunk in Png::text_chunks(options) {
+            chunk.encode(&mut writer).context("Failed to encode iTXt")?;
+        }
+        let idat_chunk_list =
+            create_idat_chunk(self, options, modifiers).context("Failed to create IDAT chunk list")?;
+        for chunk in idat_chunk_list.iter() {
+            chunk.encode(&mut writer).context("Failed to encode IDAT")?;
+        }
+        encode_chunk(&self.terminator.inner, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The method returns the chunks other than IHDR, IDAT and IEND written with the options,
+    /// i.e. those not replaced by the texts in the options.
+    fn written_misc_chunks<'a>(&'a self, options: &'a EncodeOptions) -> impl Iterator<Item = &'a Chunk> {
+        self.misc_chunks.iter().filter(|chunk| match chunk.parse_international_text() {
+            Some((keyword, _)) => !options.texts.iter().any(|(k, _)| *k == keyword),
+            None => true,
+        })
+    }
+
+    fn text_chunks(options: &EncodeOptions) -> impl Iterator<Item = Chunk> + '_ {
+        options
+            .texts
+            .iter()
+            .map(|(keyword, text)| Chunk::international_text(keyword, text))
+    }
+
     fn new(header: Header, terminator: Terminator, misc_chunks: Vec<Chunk>, data: Vec<u8>) -> Png {
         let data = share_decoded_data(data);
         Png {
@@ -55,11 +170,62 @@ impl Png {
         }
     }
 
+    /// The method creates a non-interlaced PNG image from pixel data. All scan lines are left unfiltered.
+    /// An indexed-color image gets a palette of evenly spaced gray levels.
+    /// The `width` parameter is the width of the image.
+    /// The `height` parameter is the height of the image.
+    /// The `color_type` parameter is the color type of the image.
+    /// The `bit_depth` parameter is the bit depth of the image.
+    /// The `pixels` parameter is the pixel data in row-major order, each row starting at a byte boundary.
+    pub fn from_pixels(width: u32, height: u32, color_type: ColorType, bit_depth: u8, pixels: &[u8]) -> anyhow::Result<Png> {
+        anyhow::ensure!(width > 0 && height > 0, "The width and the height should be positive");
+        if !color_type.is_valid_bit_depth(bit_depth) {
+            return Err(PngError::InvalidBitDepth)
+                .context(format!("Bit depth {} is not allowed for {:?}", bit_depth, color_type));
+        }
+        let header = Header::create(width, height, color_type, bit_depth);
+        let row_size = header.scan_line_width() - 1;
+        let expected = row_size * height as usize;
+        if pixels.len() != expected {
+            return Err(PngError::PixelDataSizeMismatch)
+                .context(format!("{} bytes of pixel data are given, while {} bytes are expected", pixels.len(), expected));
+        }
+        let mut data = Vec::with_capacity(header.scan_line_width() * height as usize);
+        for row in pixels.chunks(row_size) {
+            data.push(FilterType::None.into());
+            data.extend_from_slice(row);
+        }
+        let misc_chunks = match color_type {
+            ColorType::IndexColor => vec![Palette::grayscale(bit_depth).plte()],
+            _ => vec![],
+        };
+        Ok(Png::new(header, Terminator::default(), misc_chunks, data))
+    }
+
     fn parse(buffer: &[u8]) -> anyhow::Result<Png> {
         let png = Parser::parse(buffer)?;
         Ok(png)
     }
 
+    /// The method returns the text of the iTXt chunk with the given keyword, if any.
+    /// The `keyword` parameter is the keyword of the text.
+    pub fn text(&self, keyword: &str) -> Option<String> {
+        self.misc_chunks
+            .iter()
+            .filter_map(Chunk::parse_international_text)
+            .find(|(k, _)| k == keyword)
+            .map(|(_, text)| text)
+    }
+
+    /// The method returns the text of the iTXt chunk with the given keyword in a PNG file, if any.
+    /// Unlike [Png::text], it does not decode the image data, so it reads the text of an image whose image data is broken.
+    /// The `buffer` parameter is a byte array of a PNG file.
+    /// The `keyword` parameter is the keyword of the text.
+    pub fn read_text(buffer: &[u8], keyword: &str) -> anyhow::Result<Option<String>> {
+        let texts = Parser::parse_texts(buffer)?;
+        Ok(texts.into_iter().find(|(k, _)| k == keyword).map(|(_, text)| text))
+    }
+
     /// The method returns the width of the PNG image.
     pub fn width(&self) -> u32 {
         self.header.width()
@@ -70,6 +236,16 @@ impl Png {
         self.header.height()
     }
 
+    /// The method returns the color type of the PNG image.
+    pub fn color_type(&self) -> ColorType {
+        self.header.color_type()
+    }
+
+    /// The method returns the bit depth of the PNG image.
+    pub fn bit_depth(&self) -> u8 {
+        self.header.bit_depth()
+    }
+
     fn scan_line_width(&self) -> usize {
         self.header.scan_line_width()
     }
@@ -85,6 +261,479 @@ impl Png {
         start..end
     }
 
+    /// The method returns a copy of the decoded image data, i.e. the scan lines with their filter type bytes.
+    pub fn decoded_data(&self) -> DecodedData {
+        self.data.borrow().clone()
+    }
+
+    /// The method returns a copy of the decoded image data with the filters removed from all scan lines.
+    /// Invalid filter type bytes, which glitched images often have, are regarded as no filter.
+    pub fn unfiltered_data(&self) -> DecodedData {
+        let mut data = self.decoded_data();
+        for line in data.chunks_mut(self.scan_line_width()) {
+            if FilterType::try_from(line[0]).is_err() {
+                line[0] = FilterType::None.into();
+            }
+        }
+        let data = share_decoded_data(data);
+        let mut previous: Option<ScanLine> = None;
+        for mut line in self.scan_lines_of(&data, 0, self.height() as usize) {
+            line.remove_filter(previous.as_ref());
+            previous = Some(line);
+        }
+        data.take()
+    }
+
+    /// The method decodes the image into 16-bit RGBA pixels, in row-major order.
+    /// Samples of any bit depth are scaled to 16 bits, palettes are expanded,
+    /// and the transparency given by the tRNS chunk is applied.
+    pub fn to_rgba16(&self) -> Vec<u16> {
+        let decoder = RgbaDecoder::new(
+            self.header.color_type(),
+            self.header.bit_depth(),
+            self.find_chunk(ChunkType::PLTE),
+            self.find_chunk(ChunkType::TRNS),
+        );
+        let width = self.width() as usize;
+        self.unfiltered_data()
+            .chunks(self.scan_line_width())
+            .flat_map(|line| decoder.decode(&line[1..], width))
+            .collect()
+    }
+
+    /// The method decodes the image into 8-bit RGBA pixels, in row-major order.
+    /// 16-bit samples are rounded to 8 bits.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        pixel::to_8bit(&self.to_rgba16())
+    }
+
+    /// The method converts all the pixels to the given color type and bit depth, and leaves the scan lines unfiltered.
+    /// Colors are converted to gray by their luma, alpha is dropped for color types without alpha,
+    /// and the transparency in the tRNS chunk is turned into alpha for color types with alpha.
+    /// Converting to indexed color fails when the image has more colors than the palette can hold.
+    /// The chunks depending on the pixel layout, e.g. PLTE, tRNS, bKGD and sBIT, are dropped or rebuilt.
+    /// The `color_type` parameter is the color type to convert to.
+    /// The `bit_depth` parameter is the bit depth to convert to.
+    pub fn convert(&mut self, color_type: ColorType, bit_depth: u8) -> anyhow::Result<()> {
+        if !color_type.is_valid_bit_depth(bit_depth) {
+            return Err(PngError::InvalidBitDepth)
+                .context(format!("Bit depth {} is not allowed for {:?}", bit_depth, color_type));
+        }
+        let pixels = self.to_rgba16();
+        let palette = match color_type {
+            ColorType::IndexColor => Palette::from_rgba16(&pixels, 1 << bit_depth)?,
+            _ => Palette::default(),
+        };
+        let encoder = RgbaEncoder::new(color_type, bit_depth, &palette);
+        let rows = pixels.chunks(self.width() as usize * 4).map(|row| encoder.encode(row));
+        self.replace_pixels(color_type, bit_depth, &palette, rows);
+        Ok(())
+    }
+
+    /// The method converts the image to an indexed-color image whose palette is chosen by the given method.
+    /// The bit depth is the smallest one whose palette holds the given number of colors,
+    /// and the scan lines are left unfiltered.
+    /// The `max_colors` parameter is the largest number of colors in the palette, from 1 to 256.
+    /// The `method` parameter is the method to choose the colors.
+    /// The `dithering` parameter is the dithering applied on mapping the pixels to the colors.
+    pub fn quantize(&mut self, max_colors: usize, method: QuantizeMethod, dithering: Dithering) -> anyhow::Result<()> {
+        anyhow::ensure!((1..=256).contains(&max_colors), "The number of colors should be from 1 to 256");
+        let bit_depth = [1, 2, 4, 8].into_iter().find(|bit_depth| max_colors <= 1 << bit_depth).unwrap_or(8);
+        let width = self.width() as usize;
+        let (palette, indices) = pixel::quantize(&self.to_rgba8(), width, max_colors, method, dithering);
+        let rows = indices.chunks(width).map(|row| pixel::pack_indices(row, bit_depth));
+        self.replace_pixels(ColorType::IndexColor, bit_depth, &palette, rows);
+        Ok(())
+    }
+
+    /// The method replaces the header and the image data with unfiltered scan lines of the given layout,
+    /// and drops or rebuilds the chunks depending on the pixel layout.
+    fn replace_pixels(&mut self, color_type: ColorType, bit_depth: u8, palette: &Palette, rows: impl Iterator<Item = Vec<u8>>) {
+        let header = Header::create(self.width(), self.height(), color_type, bit_depth);
+        let mut data = Vec::with_capacity(header.scan_line_width() * self.height() as usize);
+        for row in rows {
+            data.push(FilterType::None.into());
+            data.extend(row);
+        }
+
+        let is_gray = |color_type: ColorType| matches!(color_type, ColorType::GrayScale | ColorType::GrayScaleAlpha);
+        let keeps_color_space = is_gray(self.header.color_type()) == is_gray(color_type);
+        self.misc_chunks.retain(|chunk| match &chunk.chunk_type {
+            ChunkType::Other(chunk_type) => match chunk_type {
+                b"PLTE" | b"tRNS" | b"bKGD" | b"sBIT" | b"hIST" => false,
+                b"iCCP" => keeps_color_space,
+                _ => true,
+            },
+            _ => true,
+        });
+        if color_type == ColorType::IndexColor {
+            self.misc_chunks.push(palette.plte());
+            self.misc_chunks.extend(palette.trns());
+        }
+        self.header = header;
+        *self.data.borrow_mut() = data;
+    }
+
+    /// The method rewrites the IHDR chunk and re-slices the decoded data into scan lines of the new geometry.
+    /// The data is kept as it is, so it is truncated or padded with zeros to fit the new size.
+    /// The chunks depending on the pixel layout, e.g. tRNS, bKGD and sBIT, are dropped when the layout changes,
+    /// and a grayscale palette is added when the image is reinterpreted as indexed color without a palette.
+    /// The `width` parameter is the new width.
+    /// The `height` parameter is the new height.
+    /// The `color_type` parameter is the new color type.
+    /// The `bit_depth` parameter is the new bit depth.
+    /// The `policy` parameter is the policy for the filter type bytes which are not valid after re-slicing.
+    pub fn reinterpret(
+        &mut self,
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        bit_depth: u8,
+        policy: InvalidFilterPolicy,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(width > 0 && height > 0, "The width and the height should be positive");
+        if !color_type.is_valid_bit_depth(bit_depth) {
+            return Err(PngError::InvalidBitDepth)
+                .context(format!("Bit depth {} is not allowed for {:?}", bit_depth, color_type));
+        }
+        let header = self.header.reinterpret(width, height, color_type, bit_depth);
+        let scan_line_width = header.scan_line_width();
+        {
+            let mut data = self.data.borrow_mut();
+            data.resize(scan_line_width * height as usize, 0);
+            for line in data.chunks_mut(scan_line_width) {
+                line[0] = policy.resolve(line[0]);
+            }
+        }
+
+        let keeps_layout = self.header.color_type() == color_type && self.header.bit_depth() == bit_depth;
+        let keeps_palette = !matches!(color_type, ColorType::GrayScale | ColorType::GrayScaleAlpha);
+        self.misc_chunks.retain(|chunk| match &chunk.chunk_type {
+            ChunkType::Other(chunk_type) => match chunk_type {
+                b"tRNS" | b"bKGD" | b"sBIT" => keeps_layout,
+                b"PLTE" | b"hIST" => keeps_palette,
+                _ => true,
+            },
+            _ => true,
+        });
+        if color_type == ColorType::IndexColor && self.find_chunk(ChunkType::PLTE).is_none() {
+            self.misc_chunks.push(Palette::grayscale(bit_depth).plte());
+        }
+        self.header = header;
+        Ok(())
+    }
+
+    /// The method copies scan lines over other scan lines, clamping the lines to the image.
+    /// The `src` parameter is the index of the first scan line to copy.
+    /// The `dst` parameter is the index of the first scan line to overwrite.
+    /// The `lines` parameter is the number of the scan lines to copy.
+    /// The `with_filter` parameter is whether the filter type bytes are copied with the pixel data.
+    pub fn copy_lines(&mut self, src: usize, dst: usize, lines: usize, with_filter: bool) {
+        let width = self.scan_line_width();
+        let mut data = self.data.borrow_mut();
+        let height = (self.height() as usize).min(data.len() / width);
+        if src >= height || dst >= height {
+            return;
+        }
+        let lines = lines.min(height - src.max(dst));
+        let skip = if with_filter { 0 } else { 1 };
+        let source = data[src * width..(src + lines) * width].to_vec();
+        for (line, source) in source.chunks(width).enumerate() {
+            let start = (dst + line) * width;
+            data[start + skip..start + width].copy_from_slice(&source[skip..]);
+        }
+    }
+
+    /// The method replaces the matches of the pattern in the pixel data of the scan lines in the scope.
+    /// Matches do not span scan lines, and the filter type bytes are left as they are.
+    /// It returns the number of the replaced matches.
+    /// The `pattern` parameter is the pattern to search.
+    /// The `replacement` parameter is the bytes to replace the matches with.
+    /// The `scope` parameter is the scan lines to search.
+    pub fn replace_bytes(&mut self, pattern: &BytePattern, replacement: &[u8], scope: LineScope) -> usize {
+        let range = scope.range(self.height());
+        self.scan_lines_from(range.start, range.len())
+            .iter_mut()
+            .map(|line| line.replace_bytes(pattern, replacement))
+            .sum()
+    }
+
+    /// The method crops the image to the given rectangle, and leaves the scan lines unfiltered.
+    /// The `x` parameter is the horizontal position of the left edge of the rectangle.
+    /// The `y` parameter is the vertical position of the top edge of the rectangle.
+    /// The `width` parameter is the width of the rectangle.
+    /// The `height` parameter is the height of the rectangle.
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            x.saturating_add(width) <= self.width() && y.saturating_add(height) <= self.height(),
+            "The rectangle should be inside the image of {}x{}",
+            self.width(),
+            self.height()
+        );
+        self.remap(width, height, None, |dx, dy| Some((x + dx, y + dy)))
+    }
+
+    /// The method pads the image with pixels of the given value, and leaves the scan lines unfiltered.
+    /// The `top` parameter is the number of the rows added above the image.
+    /// The `right` parameter is the number of the columns added to the right of the image.
+    /// The `bottom` parameter is the number of the rows added below the image.
+    /// The `left` parameter is the number of the columns added to the left of the image.
+    /// The `fill` parameter is the pixel to fill the added area with, which should match the color type and the bit depth.
+    pub fn pad(&mut self, top: u32, right: u32, bottom: u32, left: u32, fill: Pixel) -> anyhow::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        let padded_width = width.checked_add(left).and_then(|w| w.checked_add(right));
+        let padded_height = height.checked_add(top).and_then(|h| h.checked_add(bottom));
+        let (Some(padded_width), Some(padded_height)) = (padded_width, padded_height) else {
+            anyhow::bail!("The padded image is too large");
+        };
+        self.remap(padded_width, padded_height, Some(fill), |x, y| {
+            let inside = (left..left + width).contains(&x) && (top..top + height).contains(&y);
+            inside.then(|| (x - left, y - top))
+        })
+    }
+
+    /// The method resizes the image with the nearest-neighbour interpolation, and leaves the scan lines unfiltered.
+    /// The `width` parameter is the new width.
+    /// The `height` parameter is the new height.
+    pub fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        let (source_width, source_height) = (self.width() as u64, self.height() as u64);
+        self.remap(width, height, None, |x, y| {
+            let source_x = x as u64 * source_width / width as u64;
+            let source_y = y as u64 * source_height / height as u64;
+            Some((source_x as u32, source_y as u32))
+        })
+    }
+
+    /// The method splits the image into grayscale images, one per channel, in the order the channels are stored.
+    /// An indexed-color image is split into the red, green and blue channels of its palette colors,
+    /// and the alpha channel when it has a tRNS chunk, at 8 bits.
+    /// The other chunks are not copied to the channel images.
+    pub fn split_channels(&self) -> anyhow::Result<Vec<Png>> {
+        let (width, height) = (self.width() as usize, self.height());
+        let (rows, channels, bit_depth): (Vec<Vec<u8>>, usize, u8) = match self.color_type() {
+            ColorType::IndexColor => {
+                let channels = if self.find_chunk(ChunkType::TRNS).is_some() { 4 } else { 3 };
+                let rows = self
+                    .to_rgba8()
+                    .chunks(width * 4)
+                    .map(|row| row.chunks(4).flat_map(|rgba| rgba[..channels].to_vec()).collect())
+                    .collect();
+                (rows, channels, 8)
+            }
+            color_type => {
+                let rows = self
+                    .unfiltered_data()
+                    .chunks(self.scan_line_width())
+                    .map(|line| line[1..].to_vec())
+                    .collect();
+                (rows, color_type.channels(), self.bit_depth())
+            }
+        };
+        (0..channels)
+            .map(|channel| {
+                let pixels: Vec<u8> = rows
+                    .iter()
+                    .flat_map(|row| pixel::extract_channel(row, channels, channel, bit_depth, width))
+                    .collect();
+                Png::from_pixels(width as u32, height, ColorType::GrayScale, bit_depth, &pixels)
+            })
+            .collect()
+    }
+
+    /// The method creates an image from grayscale images, each of which becomes a channel.
+    /// One, two, three and four images make a grayscale, grayscale with alpha, truecolor and truecolor with alpha image respectively.
+    /// The scan lines are left unfiltered.
+    /// The `channels` parameter is the grayscale images of the same size and bit depth, in the order the channels are stored.
+    pub fn merge_channels(channels: &[&Png]) -> anyhow::Result<Png> {
+        let color_type = match channels.len() {
+            1 => ColorType::GrayScale,
+            2 => ColorType::GrayScaleAlpha,
+            3 => ColorType::TrueColor,
+            4 => ColorType::TrueColorAlpha,
+            count => anyhow::bail!("{} channels are given, while 1 to 4 channels are expected", count),
+        };
+        let first = channels[0];
+        let (width, height, bit_depth) = (first.width(), first.height(), first.bit_depth());
+        for channel in channels {
+            if channel.color_type() != ColorType::GrayScale {
+                return Err(PngError::InvalidColorType)
+                    .context(format!("A channel should be grayscale, while {:?} is given", channel.color_type()));
+            }
+            anyhow::ensure!(
+                (channel.width(), channel.height(), channel.bit_depth()) == (width, height, bit_depth),
+                "The channels should have the same size and bit depth"
+            );
+        }
+
+        let data: Vec<DecodedData> = channels.iter().map(|channel| channel.unfiltered_data()).collect();
+        let scan_line_width = first.scan_line_width();
+        let mut pixels = vec![];
+        for y in 0..height as usize {
+            let range = y * scan_line_width + 1..(y + 1) * scan_line_width;
+            let rows: Vec<&[u8]> = data.iter().map(|data| &data[range.clone()]).collect();
+            pixels.extend(pixel::interleave_channels(&rows, bit_depth, width as usize));
+        }
+        Png::from_pixels(width, height, color_type, bit_depth, &pixels)
+    }
+
+    /// The method rotates the image by 90 degrees clockwise, and leaves the scan lines unfiltered.
+    pub fn rotate90(&mut self) -> anyhow::Result<()> {
+        let height = self.height();
+        self.remap(height, self.width(), None, |x, y| Some((y, height - 1 - x)))
+    }
+
+    /// The method rotates the image by 180 degrees, and leaves the scan lines unfiltered.
+    pub fn rotate180(&mut self) -> anyhow::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        self.remap(width, height, None, |x, y| Some((width - 1 - x, height - 1 - y)))
+    }
+
+    /// The method rotates the image by 270 degrees clockwise, i.e. 90 degrees counterclockwise,
+    /// and leaves the scan lines unfiltered.
+    pub fn rotate270(&mut self) -> anyhow::Result<()> {
+        let width = self.width();
+        self.remap(self.height(), width, None, |x, y| Some((width - 1 - y, x)))
+    }
+
+    /// The method flips the image horizontally, and leaves the scan lines unfiltered.
+    pub fn flip_horizontal(&mut self) -> anyhow::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        self.remap(width, height, None, |x, y| Some((width - 1 - x, y)))
+    }
+
+    /// The method flips the image vertically, and leaves the scan lines unfiltered.
+    pub fn flip_vertical(&mut self) -> anyhow::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        self.remap(width, height, None, |x, y| Some((x, height - 1 - y)))
+    }
+
+    /// The method rebuilds the image in the given size from the pixels of the unfiltered image.
+    /// The pixels are copied as they are stored, so the chunks such as PLTE and tRNS are kept.
+    /// The rebuilt image is not interlaced, and the method fails when the image data lacks some scan lines.
+    /// The `width` parameter is the new width.
+    /// The `height` parameter is the new height.
+    /// The `fill` parameter is the pixel for the positions without a source pixel.
+    /// The `source` parameter is the function returning the position of the source pixel of a position, if any.
+    fn remap(
+        &mut self,
+        width: u32,
+        height: u32,
+        fill: Option<Pixel>,
+        source: impl Fn(u32, u32) -> Option<(u32, u32)>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(width > 0 && height > 0, "The width and the height should be positive");
+        let (color_type, bit_depth) = (self.color_type(), self.bit_depth());
+        let bits_per_pixel = color_type.bit_per_pixel(bit_depth);
+        let mut fill_pixel = vec![0; bits_per_pixel.div_ceil(8)];
+        if let Some(fill) = fill {
+            fill.write(&mut fill_pixel, 0, color_type, bit_depth)?;
+        }
+
+        let unfiltered = self.unfiltered_data();
+        let source_width = self.scan_line_width();
+        anyhow::ensure!(
+            unfiltered.len() >= source_width * self.height() as usize,
+            "The image data should have {} scan lines",
+            self.height()
+        );
+        let header = Header::create(width, height, color_type, bit_depth);
+        let scan_line_width = header.scan_line_width();
+        let mut data = vec![0; scan_line_width * height as usize];
+        for (y, line) in data.chunks_mut(scan_line_width).enumerate() {
+            line[0] = FilterType::None.into();
+            let row = &mut line[1..];
+            for x in 0..width {
+                match source(x, y as u32) {
+                    Some((source_x, source_y)) => {
+                        let start = source_y as usize * source_width + 1;
+                        let source_row = &unfiltered[start..start + source_width - 1];
+                        pixel::copy(source_row, source_x as usize, row, x as usize, bits_per_pixel);
+                    }
+                    None => pixel::copy(&fill_pixel, 0, row, x as usize, bits_per_pixel),
+                }
+            }
+        }
+        self.header = header;
+        *self.data.borrow_mut() = data;
+        Ok(())
+    }
+
+    /// The method returns the pixel at the given position, or `None` when it is out of the image.
+    /// The samples are the bytes stored in the scan line, so they are raw filtered values unless the filter type is None.
+    /// The `x` parameter is the horizontal position of the pixel.
+    /// The `y` parameter is the index of the scan line.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Pixel> {
+        if x >= self.width() {
+            return None;
+        }
+        self.scan_lines_from(y as usize, 1).first()?.get_pixel(x as usize)
+    }
+
+    /// The method returns a read-only view of the scan lines, showing their pixel values with the filters removed.
+    /// The `from` parameter is the index of the first scan line in the view.
+    /// The `lines` parameter is the number of scan lines in the view.
+    pub fn reconstructed_view(&self, from: usize, lines: usize) -> ReconstructedView {
+        ReconstructedView::new(
+            self.data.clone(),
+            from..from.saturating_add(lines),
+            self.scan_line_width(),
+            self.header.color_type(),
+            self.header.bit_depth(),
+        )
+    }
+
+    fn find_chunk(&self, chunk_type: [u8; 4]) -> Option<&Chunk> {
+        self.misc_chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type == ChunkType::Other(chunk_type))
+    }
+
+    fn scan_lines_of(&self, data: &SharedDecodedData, from: usize, lines: usize) -> Vec<ScanLine> {
+        let color_type = self.header.color_type();
+        let bit_depth = self.header.bit_depth();
+        (0..lines)
+            .map(|index| {
+                let index = from + index;
+                let range = self.scan_line_range(index, 1);
+                let mem_range = MemoryRange::new(data.clone(), range, color_type, bit_depth);
+                // try_from の結果をそのまま返す
+                ScanLine::try_from(mem_range)
+            })
+            // Result::ok を filter_map に渡すことで、Ok(value) は Some(value) に、
+            // Err(_) は None に変換され、無視される。より慣用的で簡潔な書き方。
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    /// The method returns the offset in the encoded file where a byte of a scan line is written
+    /// when the image is encoded with the options.
+    /// It returns `None` unless the options write stored blocks with fdeflate, without a level, a preset dictionary
+    /// nor token glitches, which move the bytes.
+    /// The `scan_line_index` parameter is the index of the scan line.
+    /// The `index` parameter is the index of the byte in the pixel data of the scan line.
+    /// The `options` parameter is the options to encode the image with.
+    pub fn stored_offset(&self, scan_line_index: usize, index: usize, options: &EncodeOptions) -> Option<usize> {
+        if !options.keeps_stored_layout() {
+            return None;
+        }
+        if scan_line_index >= self.height() as usize || index + 1 >= self.scan_line_width() {
+            return None;
+        }
+        let mut offset = encoder::stored_offset(self.index_of(scan_line_index) + 1 + index);
+        let mut base = SIGNATURE.len()
+            + self.header.inner.consumed_size()
+            + self.written_misc_chunks(options).map(Chunk::consumed_size).sum::<usize>()
+            + Png::text_chunks(options).map(|chunk| chunk.consumed_size()).sum::<usize>();
+        for size in options.idat_split.chunk_sizes(encoder::stored_size(self.data.borrow().len())) {
+            if offset < size {
+                return Some(base + 8 + offset);
+            }
+            offset -= size;
+            base += size + 12;
+        }
+        None
+    }
+
     /// The method removes filter from all scan lines.
     ///
     /// # Example
@@ -157,6 +806,111 @@ impl Png {
             }
         }
     }
+
+    /// The method converts the filter of the scan lines in specified region, i.e. reconstructs the pixels of each scan line
+    /// and filters them with the new filter type, so the pixels stay the same.
+    /// Invalid filter type bytes are regarded as no filter, and the scan lines are clamped to the image.
+    /// The `filter_type` parameter is the new filter type.
+    /// The `from` parameter is the index of the first scan line.
+    /// The `lines` parameter is the number of scan lines.
+    pub fn convert_filter_from(&mut self, filter_type: FilterType, from: u32, lines: u32) {
+        self.convert_filter_with(from, lines, |_| filter_type);
+    }
+
+    /// The method converts the filter of each scan line in specified region to the one the `select` function returns
+    /// for the index of the scan line. Like [Png::convert_filter_from], the pixels stay the same.
+    fn convert_filter_with<F>(&mut self, from: u32, lines: u32, mut select: F)
+    where
+        F: FnMut(usize) -> FilterType,
+    {
+        let range = LineScope::Lines { from, lines }.range(self.height());
+        if range.is_empty() {
+            return;
+        }
+        let start = range.start.saturating_sub(1);
+        let rows: Vec<Vec<u8>> = self.reconstructed_view(start, range.end - start).iter().collect();
+        let bpp = self.color_type().bit_per_pixel(self.bit_depth()).div_ceil(8);
+        let width = self.scan_line_width();
+        let mut data = self.data.borrow_mut();
+        for y in range {
+            let filter_type = select(y);
+            let mut row = rows[y - start].clone();
+            let previous = (y > start).then(|| rows[y - start - 1].as_slice());
+            scan_line::filter_row(filter_type, &mut row, previous, bpp);
+            data[y * width] = filter_type.into();
+            data[y * width + 1..(y + 1) * width].copy_from_slice(&row);
+        }
+    }
+
+    /// The method grafts the filter type on the scan lines in specified region, i.e. overwrites only their filter type bytes.
+    /// The pixel data is left as it is and decoded with the new filter, which changes the pixels of the scan lines,
+    /// and through the Up, Average and Paeth filters, those of the following scan lines.
+    /// The `filter_type` parameter is the new filter type.
+    /// The `from` parameter is the index of the first scan line.
+    /// The `lines` parameter is the number of scan lines.
+    pub fn graft_filter_from(&mut self, filter_type: FilterType, from: u32, lines: u32) {
+        let range = LineScope::Lines { from, lines }.range(self.height());
+        for mut line in self.scan_lines_from(range.start, range.len()) {
+            line.set_filter_type(filter_type);
+        }
+    }
+
+    /// The method applies to each scan line in specified region the filter the heuristic chooses.
+    /// The scan lines and the one preceding the region are expected to be unfiltered.
+    /// The `heuristic` parameter is the heuristic choosing the filter of each scan line.
+    /// The `from` parameter is the index of the first scan line.
+    /// The `lines` parameter is the number of scan lines.
+    pub fn apply_adaptive_filter_from(&mut self, heuristic: FilterHeuristic, from: u32, lines: u32) {
+        self.apply_filter_with(from, lines, |_, line, previous| {
+            let filters = [
+                FilterType::None,
+                FilterType::Sub,
+                FilterType::Up,
+                FilterType::Average,
+                FilterType::Paeth,
+            ];
+            let mut best = (FilterType::None, f64::MAX);
+            for filter_type in filters {
+                let score = heuristic.score(&line.filtered(filter_type, previous));
+                if score < best.1 {
+                    best = (filter_type, score);
+                }
+            }
+            best.0
+        });
+    }
+
+    /// The method applies to each scan line the filter assigned by the given plan.
+    /// The scan lines are reconstructed first, so that the pixels stay the same whatever filters they had.
+    pub fn apply_filter_plan(&mut self, plan: &FilterPlan) {
+        let lines = plan.lines(self.height());
+        self.convert_filter_with(0, lines, |index| plan.filter_type(index).unwrap_or(FilterType::None));
+    }
+
+    /// The method applies a filter to each scan line in specified region, from the last line to the first one,
+    /// so that every line is filtered against its unfiltered predecessor.
+    /// The `select` function is called with the index of a scan line, the scan line, and its predecessor,
+    /// and returns the filter to apply.
+    fn apply_filter_with<F>(&mut self, from: u32, lines: u32, mut select: F)
+    where
+        F: FnMut(usize, &mut ScanLine, Option<&ScanLine>) -> FilterType,
+    {
+        let range = LineScope::Lines { from, lines }.range(self.height());
+        if range.is_empty() {
+            return;
+        }
+        let start = range.start.saturating_sub(1);
+        let mut scan_lines = self.scan_lines_from(start, range.end - start);
+        while let Some(mut line) = scan_lines.pop() {
+            let index = start + scan_lines.len();
+            if index < range.start {
+                break;
+            }
+            let previous = scan_lines.last();
+            let filter_type = select(index, &mut line, previous);
+            line.apply_filter(filter_type, previous);
+        }
+    }
 }
 
 impl TryFrom<&[u8]> for Png {
@@ -187,22 +941,8 @@ impl Transpose for Png {
 }
 
 impl Encode for Png {
-    fn encode(&self, mut writer: impl std::io::Write) -> anyhow::Result<()> {
-        writer.write_all(SIGNATURE)?;
-        self.header
-            .encode(&mut writer)
-            .context("Failed to encode IHDR")?;
-        for chunk in self.misc_chunks.iter() {
-            chunk.encode(&mut writer)?;
-        }
-        let idat_chunk_list =
-            create_idat_chunk(self).context("Failed to create IDAT chunk list")?;
-        for chunk in idat_chunk_list.iter() {
-            chunk.encode(&mut writer).context("Failed to encode IDAT")?;
-        }
-        self.terminator.encode(&mut writer)?;
-        writer.flush()?;
-        Ok(())
+    fn encode(&self, writer: impl std::io::Write) -> anyhow::Result<()> {
+        self.encode_with_options(writer, &EncodeOptions::default())
     }
 }
 
@@ -221,38 +961,34 @@ impl Scan for Png {
     }
 
     fn scan_lines_from(&self, from: usize, lines: usize) -> Vec<ScanLine> {
-        let color_type = self.header.color_type();
-        let bit_depth = self.header.bit_depth();
-        (0..lines)
-            .map(|index| {
-                let index = from + index;
-                let range = self.scan_line_range(index, 1);
-                let mem_range = MemoryRange::new(self.data.clone(), range, color_type, bit_depth);
-                // try_from の結果をそのまま返す
-                ScanLine::try_from(mem_range)
-            })
-            // Result::ok を filter_map に渡すことで、Ok(value) は Some(value) に、
-            // Err(_) は None に変換され、無視される。より慣用的で簡潔な書き方。
-            .filter_map(Result::ok)
-            .collect()
+        self.scan_lines_of(&self.data, from, lines)
     }
 }
 
-fn create_idat_chunk(png: &Png) -> anyhow::Result<Vec<Chunk>> {
+fn create_idat_chunk(png: &Png, options: &EncodeOptions, modifiers: Modifiers) -> anyhow::Result<Vec<Chunk>> {
     let mut list = vec![];
 
-    let mut encoder = fdeflate::Compressor::new(vec![])?;
-    encoder.write_data(&png.data.borrow())?;
-    let buffer = encoder.finish()?;
-
-    let mut crc = crc32fast::Hasher::new();
-    crc.update(ChunkType::IDAT);
-    crc.update(&buffer);
-    let crc = crc.finalize().to_be_bytes();
-
-    let chunk = Chunk::new(ChunkType::Data, buffer, crc);
+    let mut buffer = encoder::deflate(&png.data.borrow(), options)?;
+    encoder::glitch_tokens(&mut buffer, options, modifiers.tokens)?;
+    encoder::glitch_huffman(&mut buffer, options, modifiers.huffman)?;
+    let original = match options.crc_policy {
+        ChecksumPolicy::Stale => options.idat_split.split(buffer.clone()),
+        ChecksumPolicy::Recompute => vec![],
+    };
+    encoder::glitch_compressed(&mut buffer, options, modifiers.compressed);
+    if options.adler32_policy == ChecksumPolicy::Recompute {
+        let dictionary = options.zlib_header.dictionary.as_deref().unwrap_or_default();
+        encoder::recompute_adler32(&mut buffer, png.data.borrow().len(), dictionary);
+    }
 
-    list.push(chunk);
+    for (index, buffer) in options.idat_split.split(buffer).into_iter().enumerate() {
+        let mut chunk = Chunk::new(ChunkType::Data, buffer, [0; 4]);
+        chunk.crc = match original.get(index) {
+            Some(data) => Chunk::new(ChunkType::Data, data.clone(), [0; 4]).compute_crc(),
+            None => chunk.compute_crc(),
+        };
+        list.push(chunk);
+    }
     Ok(list)
 }
 
@@ -295,4 +1031,700 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_encode_with_options() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let strategies = [
+            Strategy::Fast,
+            Strategy::Default,
+            Strategy::Best,
+            Strategy::HuffmanOnly,
+            Strategy::Rle,
+        ];
+        for strategy in strategies {
+            let mut buffer = vec![];
+            png.encode_with_options(&mut buffer, &EncodeOptions::new(strategy))?;
+            let another = Png::parse(&buffer)?;
+            assert_eq!(*png.data.borrow(), *another.data.borrow());
+        }
+
+        let options = EncodeOptions {
+            backend: Some(DeflateBackend::Fdeflate),
+            ..EncodeOptions::new(Strategy::Best)
+        };
+        assert!(png.encode_with_options(vec![], &options).is_err());
+
+        // A level sends the stored strategy to miniz_oxide, which fdeflate does not support.
+        let options = EncodeOptions {
+            level: Some(0),
+            ..EncodeOptions::new(Strategy::Stored)
+        };
+        let mut buffer = vec![];
+        png.encode_with_options(&mut buffer, &options)?;
+        assert_eq!(*png.data.borrow(), *Png::parse(&buffer)?.data.borrow());
+        let stored_size = encoder::stored_size(png.data.borrow().len());
+        let chunk = &create_idat_chunk(&png, &options, Modifiers::default())?[0];
+        assert!(chunk.length() >= stored_size);
+        let compressing = EncodeOptions {
+            level: Some(9),
+            ..options.clone()
+        };
+        assert!(png.encode_with_options(vec![], &compressing).is_err());
+        let options = EncodeOptions {
+            backend: Some(DeflateBackend::Fdeflate),
+            ..options
+        };
+        assert!(png.encode_with_options(vec![], &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_idat_chunk_with_split() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let compressed_size: usize = create_idat_chunk(&png, &EncodeOptions::default(), Modifiers::default())?
+            .iter()
+            .map(|chunk| chunk.length())
+            .sum();
+
+        let options = EncodeOptions {
+            idat_split: IdatSplit::Fixed(8192),
+            ..EncodeOptions::default()
+        };
+        let chunks = create_idat_chunk(&png, &options, Modifiers::default())?;
+        assert_eq!(chunks.len(), compressed_size.div_ceil(8192));
+        assert!(chunks.iter().all(|chunk| chunk.length() <= 8192));
+
+        let options = EncodeOptions {
+            idat_split: IdatSplit::Random { min: 100, max: 1000, seed: 42 },
+            ..EncodeOptions::default()
+        };
+        let mut buffer = vec![];
+        png.encode_with_options(&mut buffer, &options)?;
+        let another = Png::parse(&buffer)?;
+        assert_eq!(*png.data.borrow(), *another.data.borrow());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_idat_chunk_with_compressed_glitches() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let original = create_idat_chunk(&png, &EncodeOptions::default(), Modifiers::default())?;
+
+        let options = EncodeOptions {
+            compressed_glitches: vec![
+                CompressedGlitch::Replace { index: 0, value: 0xFF },
+                CompressedGlitch::RandomReplace { times: 10, seed: 1 },
+            ],
+            ..EncodeOptions::default()
+        };
+        let mut length = 0;
+        let mut modifier = |data: &mut Vec<u8>| {
+            length = data.len();
+            data[1] = 0xFF;
+        };
+        let modifiers = Modifiers {
+            compressed: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        let glitched = create_idat_chunk(&png, &options, modifiers)?;
+        let original = &original[0];
+        let glitched = &glitched[0];
+        assert_eq!(original.length(), glitched.length());
+        assert_eq!(length + 6, glitched.length());
+        assert_eq!(original.data[..2], glitched.data[..2]);
+        assert_eq!(glitched.data[2..4], [0xFF, 0xFF]);
+        assert_eq!(original.data[original.length() - 4..], glitched.data[glitched.length() - 4..]);
+
+        assert_eq!(glitched.compute_crc(), glitched.crc);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_idat_chunk_with_checksum_policy() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let original = &create_idat_chunk(&png, &EncodeOptions::default(), Modifiers::default())?[0];
+        let length = original.length();
+
+        let options = EncodeOptions {
+            adler32_policy: ChecksumPolicy::Recompute,
+            crc_policy: ChecksumPolicy::Stale,
+            ..EncodeOptions::default()
+        };
+        let unglitched = &create_idat_chunk(&png, &options, Modifiers::default())?[0];
+        assert_eq!(original.data, unglitched.data);
+        assert_eq!(original.crc, unglitched.crc);
+
+        let mut modifier = |data: &mut Vec<u8>| {
+            let index = data.len() / 2;
+            data[index] ^= 0xFF;
+        };
+        let modifiers = Modifiers {
+            compressed: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        let glitched = &create_idat_chunk(&png, &options, modifiers)?[0];
+        assert_eq!(original.crc, glitched.crc);
+        assert_ne!(glitched.compute_crc(), glitched.crc);
+        assert_ne!(original.data[length - 4..], glitched.data[length - 4..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_with_token_glitches() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let options = EncodeOptions {
+            token_glitches: vec![
+                TokenGlitch::ShiftDistance { delta: 3, times: 100, seed: 1 },
+                TokenGlitch::StretchLength { delta: -2, times: 100, seed: 2 },
+                TokenGlitch::SwapLiterals { times: 100, seed: 3 },
+            ],
+            ..EncodeOptions::default()
+        };
+        let mut count = 0;
+        let mut buffer = vec![];
+        png.encode_with_token_modifier(&mut buffer, &options, |tokens| {
+            count = tokens.len();
+            tokens.truncate(count / 2);
+        })?;
+        assert!(count > 0);
+
+        let another = Png::parse(&buffer)?;
+        assert_eq!(png.data.borrow().len(), another.data.borrow().len());
+        assert_ne!(*png.data.borrow(), *another.data.borrow());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_with_huffman_glitches() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let options = EncodeOptions {
+            strategy: Strategy::Best,
+            huffman_glitches: vec![HuffmanGlitch::SwapLiterals { times: 2, seed: 1 }],
+            ..EncodeOptions::default()
+        };
+        let original = create_idat_chunk(&png, &EncodeOptions::new(Strategy::Best), Modifiers::default())?;
+        let mut blocks = 0;
+        let mut modifier = |index: usize, _: &mut HuffmanTable| blocks = index + 1;
+        let modifiers = Modifiers {
+            huffman: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        let glitched = create_idat_chunk(&png, &options, modifiers)?;
+        assert!(blocks > 0);
+        assert_ne!(original[0].data, glitched[0].data);
+
+        // A modifier resizing the tables does not break the encoding.
+        let mut modifier = |_: usize, table: &mut HuffmanTable| {
+            table.literals.truncate(100);
+            table.distances.resize(40, 3);
+        };
+        let modifiers = Modifiers {
+            huffman: Some(&mut modifier),
+            ..Modifiers::default()
+        };
+        assert!(create_idat_chunk(&png, &options, modifiers).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_with_permuted_distances() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        let length = png.data.borrow().len();
+        for strategy in [Strategy::Fast, Strategy::Default, Strategy::Best] {
+            let mut changed = 0;
+            for seed in 0..4 {
+                let options = EncodeOptions {
+                    huffman_glitches: vec![HuffmanGlitch::PermuteDistances { seed }],
+                    ..EncodeOptions::new(strategy)
+                };
+                let chunk = &create_idat_chunk(&png, &options, Modifiers::default())?[0];
+                // Every match refers to decoded data, and the stream decodes to completion.
+                let tokens = zlib::ZlibStream::parse(&chunk.data)?.tokens();
+                let mut position = 0;
+                for token in tokens.iter() {
+                    match *token {
+                        Token::Literal(_) => position += 1,
+                        Token::Match { length, distance } => {
+                            assert!(distance as usize <= position);
+                            position += length as usize;
+                        }
+                    }
+                }
+                assert_eq!(position, length);
+                if zlib::decode(&tokens, &[]) != *png.data.borrow() {
+                    changed += 1;
+                }
+            }
+            assert!(changed > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_with_zlib_header() -> anyhow::Result<()> {
+        let png = Png::parse(include_bytes!("../../etc/none.png"))?;
+        let another = Png::parse(include_bytes!("../../etc/paeth.png"))?;
+
+        let options = EncodeOptions {
+            zlib_header: ZlibHeader {
+                window_size: Some(2),
+                level_hint: Some(3),
+                dictionary: None,
+            },
+            ..EncodeOptions::default()
+        };
+        let chunk = &create_idat_chunk(&png, &options, Modifiers::default())?[0];
+        assert_eq!(chunk.data[0], 0x28);
+        assert_eq!(chunk.data[1] >> 5, 0b110);
+        assert_eq!(((chunk.data[0] as u16) << 8 | chunk.data[1] as u16) % 31, 0);
+
+        let dictionary = another.decoded_data();
+        let options = EncodeOptions {
+            zlib_header: ZlibHeader {
+                dictionary: Some(dictionary.clone()),
+                ..ZlibHeader::default()
+            },
+            ..EncodeOptions::default()
+        };
+        let chunk = &create_idat_chunk(&png, &options, Modifiers::default())?[0];
+        assert_eq!(chunk.data[1] & 0x20, 0x20);
+        let tokens = zlib::ZlibStream::parse(&chunk.data)?.tokens();
+        assert_eq!(*png.data.borrow(), zlib::decode(&tokens, &dictionary));
+
+        // The compressed glitches leave the DICTID alone.
+        let header = chunk.data[..6].to_vec();
+        let glitched = EncodeOptions {
+            compressed_glitches: vec![CompressedGlitch::Replace { index: 0, value: 0xAA }],
+            ..options.clone()
+        };
+        let glitched = &create_idat_chunk(&png, &glitched, Modifiers::default())?[0];
+        assert_eq!(glitched.data[..6], header);
+        assert_eq!(glitched.data[6], 0xAA);
+
+        // The recomputed Adler-32 is the checksum of the data decoded with the dictionary.
+        let recomputed = EncodeOptions {
+            adler32_policy: ChecksumPolicy::Recompute,
+            ..options.clone()
+        };
+        let recomputed = &create_idat_chunk(&png, &recomputed, Modifiers::default())?[0];
+        assert_eq!(recomputed.data, chunk.data);
+        let length = recomputed.data.len();
+        assert_eq!(recomputed.data[length - 4..], zlib::adler32(&png.data.borrow()).to_be_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_adaptive_filter() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/none.png");
+        let original = Png::parse(bytes)?;
+        let mut original_size = vec![];
+        original.encode_with_options(&mut original_size, &EncodeOptions::new(Strategy::Best))?;
+
+        let heuristics = [
+            FilterHeuristic::MinSumOfAbsoluteDifferences,
+            FilterHeuristic::MinEntropy,
+            FilterHeuristic::SmallestCompressed,
+        ];
+        for heuristic in heuristics {
+            let mut png = Png::parse(bytes)?;
+            png.apply_adaptive_filter_from(heuristic, 0, png.height());
+            let mut buffer = vec![];
+            png.encode_with_options(&mut buffer, &EncodeOptions::new(Strategy::Best))?;
+            assert!(buffer.len() < original_size.len());
+
+            png.remove_filter();
+            assert_eq!(*original.data.borrow(), *png.data.borrow());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_filter_plan() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/none.png");
+        let original = Png::parse(bytes)?;
+        let pattern = vec![FilterType::Sub, FilterType::Up, FilterType::Paeth];
+        let plans = [
+            FilterPlan::Explicit(vec![FilterType::Average; 3]),
+            FilterPlan::Repeat(pattern.clone()),
+            FilterPlan::from_fn(|index| if index % 2 == 0 { FilterType::Up } else { FilterType::Average }),
+        ];
+        assert_eq!(format!("{:?}", plans[1]), "Repeat([Sub, Up, Paeth])");
+        assert_eq!(format!("{:?}", plans[2].clone()), "Custom(..)");
+        for plan in plans {
+            let mut png = Png::parse(bytes)?;
+            png.apply_filter_plan(&plan);
+            for (index, line) in png.scan_lines().iter().enumerate() {
+                let expected = plan.filter_type(index).unwrap_or(FilterType::None);
+                assert_eq!(line.filter_type(), expected);
+            }
+            png.remove_filter();
+            assert_eq!(*original.data.borrow(), *png.data.borrow());
+
+            // The plan applies to the reconstructed pixels of an image with mixed filters.
+            let mut mixed = Png::parse(bytes)?;
+            let half = mixed.height() / 2;
+            mixed.convert_filter_from(FilterType::Paeth, 0, half);
+            mixed.convert_filter_from(FilterType::Sub, half, u32::MAX);
+            mixed.apply_filter_plan(&plan);
+            assert_eq!(original.unfiltered_data(), mixed.unfiltered_data());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_filter_with_huge_lines() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/none.png");
+        let original = Png::parse(bytes)?;
+        let mut png = Png::parse(bytes)?;
+        png.apply_adaptive_filter_from(FilterHeuristic::MinEntropy, 5, u32::MAX);
+        png.apply_adaptive_filter_from(FilterHeuristic::MinEntropy, u32::MAX, u32::MAX);
+        png.remove_filter();
+        assert_eq!(*original.data.borrow(), *png.data.borrow());
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_rgba() -> anyhow::Result<()> {
+        let none = Png::parse(include_bytes!("../../etc/none.png"))?;
+        let pixels = none.to_rgba8();
+        let expected: Vec<u8> = none
+            .decoded_data()
+            .chunks(none.scan_line_width())
+            .flat_map(|line| line[1..].to_vec())
+            .collect();
+        assert_eq!(pixels, expected);
+        assert_eq!(none.to_rgba16(), pixels.iter().map(|value| *value as u16 * 257).collect::<Vec<_>>());
+        let filtered = [
+            include_bytes!("../../etc/sub.png").as_slice(),
+            include_bytes!("../../etc/up.png").as_slice(),
+            include_bytes!("../../etc/average.png").as_slice(),
+            include_bytes!("../../etc/paeth.png").as_slice(),
+        ];
+        for bytes in filtered {
+            assert_eq!(Png::parse(bytes)?.to_rgba8(), pixels);
+        }
+
+        // A 3x1 indexed-color image of 2 bits per pixel, whose third pixel is out of the palette.
+        let chunk = |chunk_type: &[u8], data: Vec<u8>| {
+            let chunk = Chunk::with_computed_crc(ChunkType::new(chunk_type)?, data);
+            let mut buffer = vec![];
+            chunk.encode(&mut buffer)?;
+            anyhow::Ok(buffer)
+        };
+        let mut bytes = SIGNATURE.to_vec();
+        bytes.extend(chunk(ChunkType::IHDR, vec![0, 0, 0, 3, 0, 0, 0, 1, 2, 3, 0, 0, 0])?);
+        bytes.extend(chunk(&ChunkType::PLTE, vec![255, 0, 0, 0, 255, 0])?);
+        bytes.extend(chunk(&ChunkType::TRNS, vec![128])?);
+        let data = vec![FilterType::None.into(), 0b0001_1000];
+        bytes.extend(chunk(ChunkType::IDAT, miniz_oxide::deflate::compress_to_vec_zlib(&data, 6))?);
+        bytes.extend(chunk(ChunkType::IEND, vec![])?);
+        let png = Png::parse(&bytes)?;
+        assert_eq!(png.to_rgba8(), vec![255, 0, 0, 128, 0, 255, 0, 255, 0, 0, 0, 255]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_pixels() -> anyhow::Result<()> {
+        let original = Png::parse(include_bytes!("../../etc/none.png"))?;
+        let pixels = original.to_rgba8();
+        let png = Png::from_pixels(original.width(), original.height(), ColorType::TrueColorAlpha, 8, &pixels)?;
+        let mut buffer = vec![];
+        png.encode(&mut buffer)?;
+        let png = Png::parse(&buffer)?;
+        assert_eq!(*png.data.borrow(), *original.data.borrow());
+
+        let png = Png::from_pixels(3, 2, ColorType::IndexColor, 2, &[0b0001_1000, 0b1110_0000])?;
+        let mut buffer = vec![];
+        png.encode(&mut buffer)?;
+        let png = Png::parse(&buffer)?;
+        assert_eq!(png.to_rgba8(), [0, 85, 170, 255, 170, 0].iter().flat_map(|v| [*v, *v, *v, 255]).collect::<Vec<_>>());
+
+        assert!(Png::from_pixels(3, 2, ColorType::TrueColor, 4, &[0; 6]).is_err());
+        assert!(Png::from_pixels(3, 2, ColorType::GrayScale, 8, &[0; 5]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel() -> anyhow::Result<()> {
+        let png = Png::from_pixels(3, 2, ColorType::GrayScale, 4, &[0x12, 0x30, 0x45, 0x60])?;
+        assert_eq!(png.pixel(2, 1), Some(Pixel::Gray(6)));
+        assert_eq!(png.pixel(3, 1), None);
+        assert_eq!(png.pixel(0, 2), None);
+
+        let mut line = png.scan_lines_from(1, 1).remove(0);
+        line.set_pixel(1, Pixel::Gray(0xA))?;
+        assert_eq!(png.decoded_data(), vec![0, 0x12, 0x30, 0, 0x4A, 0x60]);
+        assert!(line.set_pixel(1, Pixel::Gray(0x10)).is_err());
+        assert!(line.set_pixel(1, Pixel::Rgb(1, 2, 3)).is_err());
+        assert!(line.set_pixel(4, Pixel::Gray(1)).is_err());
+
+        let png = Png::from_pixels(1, 1, ColorType::TrueColorAlpha, 16, &[1, 2, 3, 4, 5, 6, 7, 8])?;
+        assert_eq!(png.pixel(0, 0), Some(Pixel::Rgba(0x0102, 0x0304, 0x0506, 0x0708)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconstructed_view() -> anyhow::Result<()> {
+        let png = Png::parse(include_bytes!("../../etc/paeth.png"))?;
+        let original = png.decoded_data();
+        let unfiltered = png.unfiltered_data();
+        let width = png.scan_line_width();
+        let height = png.height() as usize;
+
+        let view = png.reconstructed_view(height / 2, height);
+        assert_eq!(view.lines(), height / 2..height);
+        for (line, y) in view.iter().zip(view.lines()) {
+            assert_eq!(line, &unfiltered[y * width + 1..(y + 1) * width]);
+            assert_eq!(view.line(y), Some(line));
+        }
+        assert_eq!(view.line(0), None);
+        assert_eq!(view.line(height), None);
+        assert_eq!(png.decoded_data(), original);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reinterpret() -> anyhow::Result<()> {
+        let mut png = Png::from_pixels(2, 2, ColorType::TrueColor, 8, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12])?;
+        png.reinterpret(3, 2, ColorType::GrayScale, 8, InvalidFilterPolicy::Keep)?;
+        assert_eq!(png.decoded_data(), vec![0, 1, 2, 3, 4, 5, 6, 0]);
+        assert_eq!(png.header.inner.data[..10], [0, 0, 0, 3, 0, 0, 0, 2, 8, 0]);
+
+        png.reinterpret(1, 4, ColorType::GrayScale, 16, InvalidFilterPolicy::Wrap)?;
+        assert_eq!(png.decoded_data(), vec![0, 1, 2, 3, 4, 5, 1, 0, 0, 0, 0, 0]);
+
+        png.reinterpret(1, 3, ColorType::IndexColor, 8, InvalidFilterPolicy::Keep)?;
+        assert_eq!(png.decoded_data(), vec![0, 1, 2, 3, 4, 5]);
+        assert!(png.find_chunk(ChunkType::PLTE).is_some());
+
+        png.reinterpret(1, 3, ColorType::GrayScale, 8, InvalidFilterPolicy::Keep)?;
+        assert!(png.find_chunk(ChunkType::PLTE).is_none());
+        assert!(png.reinterpret(1, 3, ColorType::TrueColor, 4, InvalidFilterPolicy::Keep).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_filter() -> anyhow::Result<()> {
+        let mut png = Png::parse(include_bytes!("../../etc/paeth.png"))?;
+        let unfiltered = png.unfiltered_data();
+        let width = png.scan_line_width();
+        let filters = [FilterType::None, FilterType::Sub, FilterType::Up, FilterType::Average, FilterType::Paeth];
+        for (index, filter_type) in filters.into_iter().enumerate() {
+            png.convert_filter_from(filter_type, index as u32 * 3, 5);
+        }
+        png.convert_filter_from(FilterType::Up, png.height() - 1, 10);
+        assert_eq!(png.decoded_data()[3 * width], u8::from(FilterType::Sub));
+        assert_eq!(png.unfiltered_data(), unfiltered);
+
+        png.graft_filter_from(FilterType::Up, 1, 2);
+        assert_eq!(png.decoded_data()[width], u8::from(FilterType::Up));
+        assert_ne!(png.unfiltered_data(), unfiltered);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_lines() -> anyhow::Result<()> {
+        let mut png = Png::from_pixels(1, 3, ColorType::GrayScale, 8, &[1, 2, 3])?;
+        png.apply_filter_with(0, 1, |_, _, _| FilterType::Sub);
+        png.copy_lines(0, 1, 5, false);
+        assert_eq!(png.decoded_data(), vec![1, 1, 0, 1, 0, 2]);
+        png.copy_lines(1, 0, 1, true);
+        assert_eq!(png.decoded_data(), vec![0, 1, 0, 1, 0, 2]);
+
+        // The lines out of the image are ignored.
+        png.copy_lines(3, 0, 1, true);
+        png.copy_lines(0, 3, 1, true);
+        png.copy_lines(usize::MAX, 0, usize::MAX, false);
+        assert_eq!(png.decoded_data(), vec![0, 1, 0, 1, 0, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_geometry() -> anyhow::Result<()> {
+        let mut png = Png::from_pixels(3, 2, ColorType::GrayScale, 4, &[0x12, 0x30, 0x45, 0x60])?;
+        png.apply_filter_with(0, 2, |_, _, _| FilterType::Paeth);
+        png.crop(1, 0, 2, 2)?;
+        assert_eq!(png.decoded_data(), vec![0, 0x23, 0, 0x56]);
+
+        png.pad(1, 0, 0, 1, Pixel::Gray(0xF))?;
+        assert_eq!((png.width(), png.height()), (3, 3));
+        assert_eq!(png.decoded_data(), vec![0, 0xFF, 0xF0, 0, 0xF2, 0x30, 0, 0xF5, 0x60]);
+        assert!(png.pad(1, 1, 1, 1, Pixel::Gray(0x10)).is_err());
+
+        png.resize(6, 1)?;
+        assert_eq!(png.decoded_data(), vec![0, 0xFF, 0xFF, 0xFF]);
+        png.resize(2, 2)?;
+        assert_eq!(png.decoded_data(), vec![0, 0xFF, 0, 0xFF]);
+        assert!(png.crop(1, 1, 2, 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_channels() -> anyhow::Result<()> {
+        let png = Png::from_pixels(2, 1, ColorType::GrayScaleAlpha, 8, &[1, 2, 3, 4])?;
+        let channels = png.split_channels()?;
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].decoded_data(), vec![0, 1, 3]);
+        assert_eq!(channels[1].decoded_data(), vec![0, 2, 4]);
+
+        let channels = Png::parse(include_bytes!("../../etc/sample00.png"))?.split_channels()?;
+        let channels: Vec<&Png> = channels.iter().collect();
+        let merged = Png::merge_channels(&channels)?;
+        let original = Png::parse(include_bytes!("../../etc/sample00.png"))?;
+        assert_eq!(merged.color_type(), original.color_type());
+        assert_eq!(merged.decoded_data(), original.unfiltered_data());
+
+        assert!(Png::merge_channels(&[&png]).is_err());
+        assert!(Png::merge_channels(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate() -> anyhow::Result<()> {
+        let mut png = Png::from_pixels(3, 2, ColorType::GrayScale, 4, &[0x12, 0x30, 0x45, 0x60])?;
+        png.rotate90()?;
+        assert_eq!((png.width(), png.height()), (2, 3));
+        assert_eq!(png.decoded_data(), vec![0, 0x41, 0, 0x52, 0, 0x63]);
+        png.rotate180()?;
+        assert_eq!(png.decoded_data(), vec![0, 0x36, 0, 0x25, 0, 0x14]);
+        png.rotate270()?;
+        assert_eq!(png.decoded_data(), vec![0, 0x65, 0x40, 0, 0x32, 0x10]);
+        png.flip_horizontal()?;
+        png.flip_vertical()?;
+        assert_eq!(png.decoded_data(), vec![0, 0x12, 0x30, 0, 0x45, 0x60]);
+
+        let original = Png::parse(include_bytes!("../../etc/sample00.png"))?;
+        let mut png = Png::parse(include_bytes!("../../etc/sample00.png"))?;
+        for _ in 0..4 {
+            png.rotate90()?;
+        }
+        assert_eq!(png.decoded_data(), original.unfiltered_data());
+
+        // The rebuilt image is not interlaced.
+        png.header.inner.data[12] = 1;
+        png.flip_vertical()?;
+        assert_eq!(png.header.inner.data[12], 0);
+
+        // Image data lacking scan lines is an error instead of a panic.
+        let height = png.height() as usize;
+        png.data.borrow_mut().truncate(png.scan_line_width() * (height - 1));
+        assert!(png.rotate90().is_err());
+        assert!(png.resize(3, 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert() -> anyhow::Result<()> {
+        let original = Png::parse(include_bytes!("../../etc/sample00.png"))?;
+        let pixels = original.to_rgba8();
+        let layouts = [
+            (ColorType::TrueColorAlpha, 16),
+            (ColorType::TrueColorAlpha, 8),
+            (ColorType::TrueColor, 16),
+            (ColorType::TrueColor, 8),
+        ];
+        for (color_type, bit_depth) in layouts {
+            let mut png = Png::parse(include_bytes!("../../etc/sample00.png"))?;
+            png.convert(color_type, bit_depth)?;
+            let mut buffer = vec![];
+            png.encode(&mut buffer)?;
+            let png = Png::parse(&buffer)?;
+            assert_eq!(png.header.color_type(), color_type);
+            assert_eq!(png.header.bit_depth(), bit_depth);
+            // sample00.png is opaque, so its colors are kept.
+            assert_eq!(png.to_rgba8(), pixels);
+        }
+
+        let mut png = Png::from_pixels(2, 2, ColorType::TrueColorAlpha, 8, &[
+            255, 0, 0, 255, 0, 0, 255, 128,
+            0, 0, 255, 128, 255, 0, 0, 255,
+        ])?;
+        let rgba = png.to_rgba8();
+        png.convert(ColorType::IndexColor, 1)?;
+        assert_eq!(png.decoded_data(), vec![0, 0b0100_0000, 0, 0b1000_0000]);
+        assert_eq!(png.to_rgba8(), rgba);
+        png.convert(ColorType::GrayScale, 4)?;
+        assert_eq!(png.decoded_data(), vec![0, 0x42, 0, 0x24]);
+        assert!(png.find_chunk(ChunkType::PLTE).is_none());
+        assert!(png.convert(ColorType::IndexColor, 16).is_err());
+
+        let mut png = Png::from_pixels(3, 1, ColorType::GrayScale, 8, &[0, 1, 2])?;
+        assert!(png.convert(ColorType::IndexColor, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantize() -> anyhow::Result<()> {
+        let methods = [QuantizeMethod::MedianCut, QuantizeMethod::KMeans { iterations: 4 }];
+        let ditherings = [Dithering::None, Dithering::Ordered, Dithering::FloydSteinberg];
+        for method in methods {
+            for dithering in ditherings {
+                let mut png = Png::parse(include_bytes!("../../etc/none.png"))?;
+                png.quantize(16, method, dithering)?;
+                let mut buffer = vec![];
+                png.encode(&mut buffer)?;
+                let png = Png::parse(&buffer)?;
+                assert_eq!(png.header.color_type(), ColorType::IndexColor);
+                assert_eq!(png.header.bit_depth(), 4);
+                assert!(png.find_chunk(ChunkType::PLTE).is_some_and(|chunk| chunk.length() <= 16 * 3));
+            }
+        }
+
+        // An image with fewer colors than the limit keeps its colors.
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 255, 0, 255, 0, 128, 255, 0, 0, 255];
+        let mut png = Png::from_pixels(2, 2, ColorType::TrueColorAlpha, 8, &pixels)?;
+        png.quantize(3, QuantizeMethod::KMeans { iterations: 4 }, Dithering::FloydSteinberg)?;
+        assert_eq!(png.header.bit_depth(), 2);
+        assert_eq!(png.to_rgba8(), pixels);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stored_offset() -> anyhow::Result<()> {
+        let bytes = include_bytes!("../../etc/sample00.png");
+        let png = Png::parse(bytes)?;
+        for split in [IdatSplit::Single, IdatSplit::Fixed(10000)] {
+            let options = EncodeOptions {
+                idat_split: split,
+                texts: vec![("Comment".to_string(), "glitched".to_string())],
+                ..EncodeOptions::new(Strategy::Stored)
+            };
+            let mut buffer = vec![];
+            png.encode_with_options(&mut buffer, &options)?;
+
+            for line in [0, 1, png.height() as usize / 2, png.height() as usize - 1] {
+                let scan_line = &png.scan_lines_from(line, 1)[0];
+                for index in [0, 1, scan_line.size() - 1] {
+                    let offset = png.stored_offset(line, index, &options).unwrap();
+                    assert_eq!(Some(buffer[offset]), scan_line.index(index));
+                }
+            }
+            let another = Png::parse(&buffer)?;
+            assert_eq!(*png.data.borrow(), *another.data.borrow());
+        }
+        let options = EncodeOptions::new(Strategy::Stored);
+        assert_eq!(None, png.stored_offset(png.height() as usize, 0, &options));
+        let options = EncodeOptions {
+            zlib_header: ZlibHeader {
+                dictionary: Some(vec![0; 100]),
+                ..ZlibHeader::default()
+            },
+            ..EncodeOptions::new(Strategy::Stored)
+        };
+        assert_eq!(None, png.stored_offset(0, 0, &options));
+        let options = EncodeOptions {
+            level: Some(0),
+            ..EncodeOptions::new(Strategy::Stored)
+        };
+        assert_eq!(None, png.stored_offset(0, 0, &options));
+        Ok(())
+    }
 }
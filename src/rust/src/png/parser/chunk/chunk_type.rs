@@ -43,6 +43,12 @@ impl ChunkType {
     pub const IDAT: &'static [u8] = &[73, 68, 65, 84];
     /// The IEND chunk type.
     pub const IEND: &'static [u8] = &[73, 69, 78, 68];
+    /// The PLTE chunk type.
+    pub const PLTE: [u8; 4] = [80, 76, 84, 69];
+    /// The tRNS chunk type.
+    pub const TRNS: [u8; 4] = [116, 82, 78, 83];
+    /// The iTXt chunk type.
+    pub const ITXT: [u8; 4] = [105, 84, 88, 116];
 }
 
 impl Debug for ChunkType {
@@ -39,6 +39,70 @@ impl Chunk {
         }
     }
 
+    /// The method creates a new chunk with the CRC computed from the chunk type and the chunk data.
+    /// The `chunk_type` parameter is the type of the chunk.
+    /// The `data` parameter is the data of the chunk.
+    pub fn with_computed_crc(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
+        let mut chunk = Chunk::new(chunk_type, data, [0; 4]);
+        chunk.crc = chunk.compute_crc();
+        chunk
+    }
+
+    /// The method computes the CRC of the chunk type and the chunk data.
+    pub fn compute_crc(&self) -> [u8; 4] {
+        let mut chunk_type = vec![];
+        let _ = self.chunk_type.encode(&mut chunk_type);
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&chunk_type);
+        crc.update(&self.data);
+        crc.finalize().to_be_bytes()
+    }
+
+    /// The method writes the chunk with the given CRC instead of its own one.
+    /// The `writer` parameter is a writable buffer.
+    /// The `crc` parameter is the CRC to write.
+    pub fn encode_with_crc(&self, mut writer: impl std::io::Write, crc: [u8; 4]) -> anyhow::Result<()> {
+        writer.write_all(&(self.length() as u32).to_be_bytes())?;
+        self.chunk_type.encode(&mut writer)?;
+        writer.write_all(&self.data)?;
+        writer.write_all(&crc)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The method creates an iTXt chunk holding the given text uncompressed, without language tag.
+    /// The `keyword` parameter is the keyword of the text, 1 to 79 Latin-1 characters.
+    /// The `text` parameter is the text.
+    pub fn international_text(keyword: &str, text: &str) -> Chunk {
+        let mut data = keyword.as_bytes().to_vec();
+        // The null separator, the compression flag and method, and the empty language tag and translated keyword.
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+        Chunk::with_computed_crc(ChunkType::Other(ChunkType::ITXT), data)
+    }
+
+    /// The method returns the keyword and the text of an iTXt chunk.
+    /// It returns `None` when the chunk is not an iTXt chunk or it is malformed.
+    pub fn parse_international_text(&self) -> Option<(String, String)> {
+        if self.chunk_type != ChunkType::Other(ChunkType::ITXT) {
+            return None;
+        }
+        let mut fields = self.data.splitn(2, |byte| *byte == 0);
+        let keyword = String::from_utf8(fields.next()?.to_vec()).ok()?;
+        let rest = fields.next()?;
+        let (compression_flag, rest) = (*rest.first()?, rest.get(2..)?);
+        let mut fields = rest.splitn(3, |byte| *byte == 0);
+        let _language_tag = fields.next()?;
+        let _translated_keyword = fields.next()?;
+        let text = fields.next()?;
+        let text = if compression_flag == 0 {
+            text.to_vec()
+        } else {
+            miniz_oxide::inflate::decompress_to_vec_zlib(text).ok()?
+        };
+        Some((keyword, String::from_utf8(text).ok()?))
+    }
+
     /// The method parses a chunk from a byte array.
     /// The `buffer` parameter is a byte array of a PNG file.
     pub fn parse(buffer: &[u8]) -> anyhow::Result<Chunk> {
@@ -76,12 +140,7 @@ impl Chunk {
 }
 
 impl Encode for Chunk {
-    fn encode(&self, mut writer: impl std::io::Write) -> anyhow::Result<()> {
-        writer.write_all(&(self.length() as u32).to_be_bytes())?;
-        self.chunk_type.encode(&mut writer)?;
-        writer.write_all(&self.data)?;
-        writer.write_all(&self.crc)?;
-        writer.flush()?;
-        Ok(())
+    fn encode(&self, writer: impl std::io::Write) -> anyhow::Result<()> {
+        self.encode_with_crc(writer, self.crc)
     }
 }
@@ -1,7 +1,9 @@
 use crate::png::png_error::PngError;
+use serde::{Deserialize, Serialize};
 
 /// An enum representing the color type of a PNG image.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ColorType {
     /// Grayscale image.
     GrayScale,
@@ -16,6 +18,26 @@ pub enum ColorType {
 }
 
 impl ColorType {
+    /// The method returns the number of channels, i.e. samples per pixel.
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::GrayScale | Self::IndexColor => 1,
+            Self::GrayScaleAlpha => 2,
+            Self::TrueColor => 3,
+            Self::TrueColorAlpha => 4,
+        }
+    }
+
+    /// The method returns whether the bit depth is allowed for the color type.
+    /// The `bit_depth` parameter is the bit depth to check.
+    pub fn is_valid_bit_depth(&self, bit_depth: u8) -> bool {
+        match self {
+            Self::GrayScale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            Self::IndexColor => matches!(bit_depth, 1 | 2 | 4 | 8),
+            Self::TrueColor | Self::GrayScaleAlpha | Self::TrueColorAlpha => matches!(bit_depth, 8 | 16),
+        }
+    }
+
     /// The method returns the number of bits per pixel.
     /// The `bit_depth` parameter is the bit depth of the PNG image.
     pub fn bit_per_pixel(&self, bit_depth: u8) -> usize {
@@ -43,3 +65,15 @@ impl TryFrom<u8> for ColorType {
         }
     }
 }
+
+impl From<ColorType> for u8 {
+    fn from(value: ColorType) -> Self {
+        match value {
+            ColorType::GrayScale => 0,
+            ColorType::TrueColor => 2,
+            ColorType::IndexColor => 3,
+            ColorType::GrayScaleAlpha => 4,
+            ColorType::TrueColorAlpha => 6,
+        }
+    }
+}
@@ -20,7 +20,7 @@ pub struct Header {
 impl Header {
     fn new(width: u32, height: u32, bit_depth: u8, color_type: ColorType, inner: Chunk) -> Header {
         let metadata = MetaData::new(width, height, color_type, bit_depth);
-        let scanline_width = metadata.bits_per_scanline() / 8 + 1;
+        let scanline_width = metadata.bits_per_scanline().div_ceil(8) + 1;
         Header {
             inner,
             metadata,
@@ -28,6 +28,36 @@ impl Header {
         }
     }
 
+    /// The method creates a header of a non-interlaced image with the given metadata.
+    /// The `width` parameter is the width of the PNG image.
+    /// The `height` parameter is the height of the PNG image.
+    /// The `color_type` parameter is the color type of the PNG image.
+    /// The `bit_depth` parameter is the bit depth of the PNG image.
+    pub fn create(width: u32, height: u32, color_type: ColorType, bit_depth: u8) -> Header {
+        let mut data = vec![];
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        // The compression method, the filter method and the interlace method follow.
+        data.extend_from_slice(&[bit_depth, color_type.into(), 0, 0, 0]);
+        let inner = Chunk::with_computed_crc(ChunkType::Start, data);
+        Header::new(width, height, bit_depth, color_type, inner)
+    }
+
+    /// The method returns a copy of the header with the given metadata, keeping the other fields such as the interlace method.
+    /// The `width` parameter is the width of the PNG image.
+    /// The `height` parameter is the height of the PNG image.
+    /// The `color_type` parameter is the color type of the PNG image.
+    /// The `bit_depth` parameter is the bit depth of the PNG image.
+    pub fn reinterpret(&self, width: u32, height: u32, color_type: ColorType, bit_depth: u8) -> Header {
+        let mut data = self.inner.data.clone();
+        data[0..4].copy_from_slice(&width.to_be_bytes());
+        data[4..8].copy_from_slice(&height.to_be_bytes());
+        data[8] = bit_depth;
+        data[9] = color_type.into();
+        let inner = Chunk::with_computed_crc(ChunkType::Start, data);
+        Header::new(width, height, bit_depth, color_type, inner)
+    }
+
     /// The method returns the width of the PNG image.
     pub fn width(&self) -> u32 {
         self.metadata.width
@@ -102,3 +132,20 @@ impl Debug for Header {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scan_line_width() {
+        // A partial byte at the end of a scan line counts as a whole byte.
+        let widths = [(1, 2), (2, 2), (4, 3), (8, 4)];
+        for (bit_depth, expected) in widths {
+            let header = Header::create(3, 1, ColorType::GrayScale, bit_depth);
+            assert_eq!(header.scan_line_width(), expected);
+        }
+        assert_eq!(Header::create(9, 1, ColorType::IndexColor, 1).scan_line_width(), 3);
+        assert_eq!(Header::create(3, 1, ColorType::TrueColor, 16).scan_line_width(), 19);
+    }
+}
@@ -25,10 +25,22 @@ impl Parser {
     /// The method parses a PNG file and returns a `Png` object.
     /// The `buffer` parameter is a byte array of a PNG file.
     pub fn parse(buffer: &[u8]) -> anyhow::Result<Png> {
+        Self::parse_file(buffer)?.build()
+    }
+
+    /// The method returns the keywords and the texts of the iTXt chunks in a PNG file without decoding its image data,
+    /// so that it succeeds even if the image data is broken.
+    /// The `buffer` parameter is a byte array of a PNG file.
+    pub fn parse_texts(buffer: &[u8]) -> anyhow::Result<Vec<(String, String)>> {
+        let parser = Self::parse_file(buffer)?;
+        Ok(parser.misc.iter().filter_map(Chunk::parse_international_text).collect())
+    }
+
+    fn parse_file(buffer: &[u8]) -> anyhow::Result<Parser> {
         if buffer.starts_with(SIGNATURE) {
             let mut parser = Self::new();
             parser.parse_chunks(&buffer[8..])?;
-            parser.build()
+            Ok(parser)
         } else {
             Err(PngError::InvalidSignature).context("Invalid signature found on parsing png file.")
         }
@@ -7,6 +7,14 @@ pub struct Terminator {
     pub inner: Chunk,
 }
 
+impl Default for Terminator {
+    fn default() -> Self {
+        Terminator {
+            inner: Chunk::with_computed_crc(ChunkType::End, vec![]),
+        }
+    }
+}
+
 impl TryFrom<Chunk> for Terminator {
     type Error = anyhow::Error;
 
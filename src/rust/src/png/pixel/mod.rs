@@ -0,0 +1,274 @@
+use crate::png::parser::{Chunk, ColorType};
+use crate::png::png_error::PngError;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+pub use palette::Palette;
+pub use quantize::{quantize, Dithering, QuantizeMethod};
+
+mod palette;
+mod quantize;
+mod sample;
+
+/// An enum representing a pixel of a scan line, whose samples are the values stored at the bit depth of the image,
+/// from 1 to 16 bits, without scaling.
+/// A 16-bit sample is stored in two bytes in big endian, and samples narrower than a byte are packed
+/// from the most significant bits of a byte.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pixel {
+    /// A grayscale pixel.
+    Gray(u16),
+    /// A grayscale pixel with alpha.
+    GrayAlpha(u16, u16),
+    /// A truecolor pixel.
+    Rgb(u16, u16, u16),
+    /// A truecolor pixel with alpha.
+    Rgba(u16, u16, u16, u16),
+    /// An indexed-color pixel, i.e. an index of a palette entry.
+    Indexed(u8),
+}
+
+impl Pixel {
+    /// The method returns the color type of the image the pixel belongs to.
+    pub fn color_type(&self) -> ColorType {
+        match self {
+            Self::Gray(_) => ColorType::GrayScale,
+            Self::GrayAlpha(_, _) => ColorType::GrayScaleAlpha,
+            Self::Rgb(_, _, _) => ColorType::TrueColor,
+            Self::Rgba(_, _, _, _) => ColorType::TrueColorAlpha,
+            Self::Indexed(_) => ColorType::IndexColor,
+        }
+    }
+
+    /// The method returns the samples of the pixel in the order they are stored.
+    pub fn samples(&self) -> Vec<u16> {
+        match *self {
+            Self::Gray(v) => vec![v],
+            Self::GrayAlpha(v, a) => vec![v, a],
+            Self::Rgb(r, g, b) => vec![r, g, b],
+            Self::Rgba(r, g, b, a) => vec![r, g, b, a],
+            Self::Indexed(index) => vec![index as u16],
+        }
+    }
+
+    fn from_samples(color_type: ColorType, samples: &[u16]) -> Pixel {
+        match color_type {
+            ColorType::GrayScale => Self::Gray(samples[0]),
+            ColorType::GrayScaleAlpha => Self::GrayAlpha(samples[0], samples[1]),
+            ColorType::TrueColor => Self::Rgb(samples[0], samples[1], samples[2]),
+            ColorType::TrueColorAlpha => Self::Rgba(samples[0], samples[1], samples[2], samples[3]),
+            ColorType::IndexColor => Self::Indexed(samples[0] as u8),
+        }
+    }
+
+    /// The method reads a pixel from the pixel data of a scan line.
+    /// It returns `None` when the pixel is out of the scan line.
+    /// The `row` parameter is the pixel data of a scan line without its filter type byte.
+    /// The `x` parameter is the index of the pixel.
+    /// The `color_type` parameter is the color type of the image.
+    /// The `bit_depth` parameter is the bit depth of the image.
+    pub fn read(row: &[u8], x: usize, color_type: ColorType, bit_depth: u8) -> Option<Pixel> {
+        let channels = color_type.channels();
+        if (x + 1) * color_type.bit_per_pixel(bit_depth) > row.len() * 8 {
+            return None;
+        }
+        let samples: Vec<u16> = (0..channels)
+            .map(|channel| sample::read(row, bit_depth, x * channels + channel))
+            .collect();
+        Some(Self::from_samples(color_type, &samples))
+    }
+
+    /// The method writes the pixel into the pixel data of a scan line.
+    /// It fails when the pixel does not match the color type, any sample exceeds the bit depth,
+    /// or the pixel is out of the scan line.
+    /// The `row` parameter is the pixel data of a scan line without its filter type byte.
+    /// The `x` parameter is the index of the pixel.
+    /// The `color_type` parameter is the color type of the image.
+    /// The `bit_depth` parameter is the bit depth of the image.
+    pub fn write(&self, row: &mut [u8], x: usize, color_type: ColorType, bit_depth: u8) -> anyhow::Result<()> {
+        if self.color_type() != color_type {
+            return Err(PngError::InvalidPixel)
+                .context(format!("{:?} is given for an image of {:?}", self, color_type));
+        }
+        let samples = self.samples();
+        if samples.iter().any(|sample| *sample > sample::max_value(bit_depth)) {
+            return Err(PngError::InvalidPixel)
+                .context(format!("{:?} has a sample exceeding {} bits", self, bit_depth));
+        }
+        if (x + 1) * color_type.bit_per_pixel(bit_depth) > row.len() * 8 {
+            return Err(PngError::InvalidPixel).context(format!("The pixel #{} is out of the scan line", x));
+        }
+        let channels = color_type.channels();
+        for (channel, value) in samples.into_iter().enumerate() {
+            sample::write(row, bit_depth, x * channels + channel, value);
+        }
+        Ok(())
+    }
+}
+
+/// A struct decoding the pixel data of scan lines into RGBA pixels.
+pub struct RgbaDecoder {
+    color_type: ColorType,
+    bit_depth: u8,
+    palette: Palette,
+    transparent: Option<Vec<u16>>,
+}
+
+impl RgbaDecoder {
+    /// The method creates a decoder for an image.
+    /// The `color_type` parameter is the color type of the image.
+    /// The `bit_depth` parameter is the bit depth of the image.
+    /// The `plte` parameter is the PLTE chunk of the image, if any.
+    /// The `trns` parameter is the tRNS chunk of the image, if any.
+    pub fn new(color_type: ColorType, bit_depth: u8, plte: Option<&Chunk>, trns: Option<&Chunk>) -> RgbaDecoder {
+        let transparent = match color_type {
+            ColorType::GrayScale | ColorType::TrueColor => trns.map(|chunk| {
+                let samples = chunk.data.len() / 2;
+                sample::unpack(&chunk.data, 16, samples)
+            }),
+            _ => None,
+        };
+        RgbaDecoder {
+            color_type,
+            bit_depth,
+            palette: Palette::new(plte, trns),
+            transparent,
+        }
+    }
+
+    /// The method decodes the pixel data of a scan line into 16-bit RGBA pixels.
+    /// The `row` parameter is the unfiltered pixel data of a scan line without its filter type byte.
+    /// The `width` parameter is the number of pixels in the scan line.
+    pub fn decode(&self, row: &[u8], width: usize) -> Vec<u16> {
+        let channels = self.color_type.channels();
+        let samples = sample::unpack(row, self.bit_depth, width * channels);
+        let scale = |sample: u16| sample::scale(sample, self.bit_depth, 16);
+        let mut pixels = Vec::with_capacity(width * 4);
+        for pixel in samples.chunks_exact(channels) {
+            let opaque = self.transparent.as_deref() != Some(pixel);
+            let alpha = if opaque { u16::MAX } else { 0 };
+            let rgba = match self.color_type {
+                ColorType::GrayScale => [scale(pixel[0]), scale(pixel[0]), scale(pixel[0]), alpha],
+                ColorType::GrayScaleAlpha => [scale(pixel[0]), scale(pixel[0]), scale(pixel[0]), scale(pixel[1])],
+                ColorType::TrueColor => [scale(pixel[0]), scale(pixel[1]), scale(pixel[2]), alpha],
+                ColorType::TrueColorAlpha => [scale(pixel[0]), scale(pixel[1]), scale(pixel[2]), scale(pixel[3])],
+                ColorType::IndexColor => self.palette.rgba(pixel[0] as usize).map(|value| sample::scale(value as u16, 8, 16)),
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+        pixels
+    }
+}
+
+/// The function converts 16-bit samples into 8-bit ones.
+/// The `samples` parameter is the 16-bit samples.
+pub fn to_8bit(samples: &[u16]) -> Vec<u8> {
+    samples.iter().map(|sample| sample::scale(*sample, 16, 8) as u8).collect()
+}
+
+/// A struct encoding 16-bit RGBA pixels into the pixel data of scan lines of a color type and a bit depth.
+pub struct RgbaEncoder<'a> {
+    color_type: ColorType,
+    bit_depth: u8,
+    palette: &'a Palette,
+    indices: HashMap<[u8; 4], usize>,
+}
+
+impl<'a> RgbaEncoder<'a> {
+    /// The method creates an encoder.
+    /// The `color_type` parameter is the color type to encode the pixels in.
+    /// The `bit_depth` parameter is the bit depth to encode the pixels in.
+    /// The `palette` parameter is the palette which indexed-color pixels refer to. It is ignored for other color types.
+    pub fn new(color_type: ColorType, bit_depth: u8, palette: &'a Palette) -> RgbaEncoder<'a> {
+        let indices = (0..palette.colors.len()).map(|index| (palette.rgba(index), index)).rev().collect();
+        RgbaEncoder {
+            color_type,
+            bit_depth,
+            palette,
+            indices,
+        }
+    }
+
+    /// The method encodes 16-bit RGBA pixels into the pixel data of a scan line without its filter type byte.
+    /// Colors are converted to gray by their luma, and alpha is dropped for color types without alpha.
+    /// Indexed-color pixels refer to the palette entry of the same color, or the nearest one.
+    /// The `pixels` parameter is the 16-bit RGBA samples of the pixels in the scan line.
+    pub fn encode(&self, pixels: &[u16]) -> Vec<u8> {
+        let scale = |sample: u16| sample::scale(sample, 16, self.bit_depth);
+        let mut samples = Vec::with_capacity(pixels.len());
+        for pixel in pixels.chunks_exact(4) {
+            let gray = || (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114 + 500) / 1000;
+            match self.color_type {
+                ColorType::GrayScale => samples.push(scale(gray() as u16)),
+                ColorType::GrayScaleAlpha => samples.extend([scale(gray() as u16), scale(pixel[3])]),
+                ColorType::TrueColor => samples.extend(pixel[..3].iter().map(|sample| scale(*sample))),
+                ColorType::TrueColorAlpha => samples.extend(pixel.iter().map(|sample| scale(*sample))),
+                ColorType::IndexColor => {
+                    let rgba = rgba8(pixel);
+                    let index = match self.indices.get(&rgba) {
+                        Some(index) => *index,
+                        None => self.palette.nearest(rgba),
+                    };
+                    samples.push(index as u16);
+                }
+            }
+        }
+        sample::pack(&samples, self.bit_depth)
+    }
+}
+
+/// The function packs palette indices into the pixel data of a scan line without its filter type byte.
+/// The `indices` parameter is the palette indices of the pixels in the scan line.
+/// The `bit_depth` parameter is the bit depth of the indexed-color image.
+pub fn pack_indices(indices: &[usize], bit_depth: u8) -> Vec<u8> {
+    let samples: Vec<u16> = indices.iter().map(|index| *index as u16).collect();
+    sample::pack(&samples, bit_depth)
+}
+
+/// The function copies a pixel from the pixel data of a scan line into that of another, as it is stored.
+/// The `source` parameter is the pixel data of the scan line to copy from, without its filter type byte.
+/// The `from` parameter is the index of the pixel to copy.
+/// The `destination` parameter is the pixel data of the scan line to copy to, without its filter type byte.
+/// The `to` parameter is the index of the pixel to overwrite.
+/// The `bits_per_pixel` parameter is the number of bits per pixel.
+pub fn copy(source: &[u8], from: usize, destination: &mut [u8], to: usize, bits_per_pixel: usize) {
+    if bits_per_pixel.is_multiple_of(8) {
+        let bytes = bits_per_pixel / 8;
+        destination[to * bytes..(to + 1) * bytes].copy_from_slice(&source[from * bytes..(from + 1) * bytes]);
+    } else {
+        // Pixels narrower than a byte have only one sample.
+        let bit_depth = bits_per_pixel as u8;
+        sample::write(destination, bit_depth, to, sample::read(source, bit_depth, from));
+    }
+}
+
+/// The function extracts the samples of a channel from the pixel data of a scan line.
+/// The `row` parameter is the pixel data of a scan line without its filter type byte.
+/// The `channels` parameter is the number of channels of the image.
+/// The `channel` parameter is the index of the channel to extract.
+/// The `bit_depth` parameter is the bit depth of the image.
+/// The `width` parameter is the number of pixels in the scan line.
+pub fn extract_channel(row: &[u8], channels: usize, channel: usize, bit_depth: u8, width: usize) -> Vec<u8> {
+    let samples: Vec<u16> = (0..width)
+        .map(|x| sample::read(row, bit_depth, x * channels + channel))
+        .collect();
+    sample::pack(&samples, bit_depth)
+}
+
+/// The function interleaves the samples of channels into the pixel data of a scan line.
+/// The `rows` parameter is the samples of each channel of the scan line, in the order the channels are stored.
+/// The `bit_depth` parameter is the bit depth of the image.
+/// The `width` parameter is the number of pixels in the scan line.
+pub fn interleave_channels(rows: &[&[u8]], bit_depth: u8, width: usize) -> Vec<u8> {
+    let samples: Vec<u16> = (0..width)
+        .flat_map(|x| rows.iter().map(move |row| sample::read(row, bit_depth, x)))
+        .collect();
+    sample::pack(&samples, bit_depth)
+}
+
+/// The function converts a 16-bit RGBA pixel into an 8-bit one.
+/// The `pixel` parameter is the 16-bit RGBA samples of the pixel.
+pub fn rgba8(pixel: &[u16]) -> [u8; 4] {
+    [0, 1, 2, 3].map(|channel| sample::scale(pixel[channel], 16, 8) as u8)
+}
@@ -0,0 +1,111 @@
+use crate::png::parser::{Chunk, ChunkType};
+use crate::png::pixel::sample;
+use crate::png::png_error::PngError;
+use anyhow::Context;
+use std::collections::HashSet;
+
+/// A struct representing the palette of an indexed-color image, read from its PLTE and tRNS chunks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    /// The RGB colors of the palette entries.
+    pub colors: Vec<[u8; 3]>,
+    /// The alpha values of the palette entries. The entries without an alpha value are opaque.
+    pub alphas: Vec<u8>,
+}
+
+impl Palette {
+    /// The method creates a palette from the PLTE and tRNS chunks.
+    /// The `plte` parameter is the PLTE chunk, if any.
+    /// The `trns` parameter is the tRNS chunk, if any.
+    pub fn new(plte: Option<&Chunk>, trns: Option<&Chunk>) -> Palette {
+        let colors = plte
+            .map(|chunk| chunk.data.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect())
+            .unwrap_or_default();
+        let alphas = trns.map(|chunk| chunk.data.clone()).unwrap_or_default();
+        Palette { colors, alphas }
+    }
+
+    /// The method creates a palette of evenly spaced gray levels, one for each index of the given bit depth.
+    /// The `bit_depth` parameter is the bit depth of the indexed-color image.
+    pub fn grayscale(bit_depth: u8) -> Palette {
+        let colors = (0..=sample::max_value(bit_depth))
+            .map(|index| [sample::scale(index, bit_depth, 8) as u8; 3])
+            .collect();
+        Palette { colors, alphas: vec![] }
+    }
+
+    /// The method creates a palette of the distinct colors of 16-bit RGBA pixels rounded to 8 bits, in order of appearance.
+    /// It fails when there are more colors than the given limit.
+    /// The `pixels` parameter is the 16-bit RGBA samples of the pixels.
+    /// The `max_colors` parameter is the largest number of colors allowed.
+    pub fn from_rgba16(pixels: &[u16], max_colors: usize) -> anyhow::Result<Palette> {
+        let mut colors = vec![];
+        let mut found = HashSet::new();
+        for pixel in pixels.chunks_exact(4) {
+            let rgba = super::rgba8(pixel);
+            if found.insert(rgba) {
+                if colors.len() == max_colors {
+                    return Err(PngError::TooManyColors)
+                        .context(format!("The image has more than {} colors", max_colors));
+                }
+                colors.push(rgba);
+            }
+        }
+        Ok(Palette::from_rgba8(&colors))
+    }
+
+    /// The method creates a palette of the given colors.
+    /// The `colors` parameter is the 8-bit RGBA colors of the palette entries.
+    pub fn from_rgba8(colors: &[[u8; 4]]) -> Palette {
+        let mut palette = Palette {
+            colors: colors.iter().map(|rgba| [rgba[0], rgba[1], rgba[2]]).collect(),
+            alphas: colors.iter().map(|rgba| rgba[3]).collect(),
+        };
+        palette.trim_alphas();
+        palette
+    }
+
+    /// The method returns the index of the palette entry closest to the given color in the RGBA space.
+    /// The `rgba` parameter is the 8-bit RGBA color.
+    pub fn nearest(&self, rgba: [u8; 4]) -> usize {
+        let distance = |index: usize| {
+            self.rgba(index)
+                .iter()
+                .zip(rgba.iter())
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+                .sum::<i32>()
+        };
+        (0..self.colors.len()).min_by_key(|index| distance(*index)).unwrap_or(0)
+    }
+
+    /// The method returns the PLTE chunk of the palette.
+    pub fn plte(&self) -> Chunk {
+        Chunk::with_computed_crc(ChunkType::Other(ChunkType::PLTE), self.colors.concat())
+    }
+
+    /// The method returns the tRNS chunk of the palette, or `None` when all the entries are opaque.
+    pub fn trns(&self) -> Option<Chunk> {
+        if self.alphas.is_empty() {
+            None
+        } else {
+            Some(Chunk::with_computed_crc(ChunkType::Other(ChunkType::TRNS), self.alphas.clone()))
+        }
+    }
+
+    /// The method drops the trailing opaque alpha values, which the tRNS chunk can omit.
+    fn trim_alphas(&mut self) {
+        while self.alphas.last() == Some(&u8::MAX) {
+            self.alphas.pop();
+        }
+    }
+
+    /// The method returns the RGBA color of a palette entry.
+    /// An index out of the palette, which glitched images often have, is mapped to opaque black.
+    /// The `index` parameter is the index of the palette entry.
+    pub fn rgba(&self, index: usize) -> [u8; 4] {
+        match self.colors.get(index) {
+            Some([r, g, b]) => [*r, *g, *b, self.alphas.get(index).copied().unwrap_or(u8::MAX)],
+            None => [0, 0, 0, u8::MAX],
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::png::pixel::Palette;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An enum representing the method to choose the colors of a palette.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizeMethod {
+    /// Splits the box of colors at the median of its longest side until there are enough boxes,
+    /// and takes the average color of each box.
+    MedianCut,
+    /// Refines the colors chosen by the median cut with the given number of iterations of k-means clustering.
+    KMeans { iterations: usize },
+}
+
+/// An enum representing the dithering applied when pixels are mapped to the colors of a palette.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dithering {
+    /// Maps each pixel to the nearest color.
+    #[default]
+    None,
+    /// Adds the threshold of a 4x4 Bayer matrix to each pixel before mapping it.
+    Ordered,
+    /// Diffuses the error of each pixel to its neighbours with the Floyd–Steinberg weights.
+    FloydSteinberg,
+}
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// The function chooses a palette for 8-bit RGBA pixels and maps the pixels to its entries.
+/// It returns the palette and the index of the palette entry of each pixel.
+/// The `pixels` parameter is the 8-bit RGBA samples of the pixels in row-major order.
+/// The `width` parameter is the number of pixels in a row.
+/// The `max_colors` parameter is the largest number of colors in the palette.
+/// The `method` parameter is the method to choose the colors.
+/// The `dithering` parameter is the dithering applied on mapping.
+pub fn quantize(pixels: &[u8], width: usize, max_colors: usize, method: QuantizeMethod, dithering: Dithering) -> (Palette, Vec<usize>) {
+    let mut histogram: HashMap<[u8; 4], usize> = HashMap::new();
+    for pixel in pixels.chunks_exact(4) {
+        *histogram.entry([pixel[0], pixel[1], pixel[2], pixel[3]]).or_default() += 1;
+    }
+    let mut histogram: Vec<([u8; 4], usize)> = histogram.into_iter().collect();
+    histogram.sort_unstable();
+
+    let mut colors = median_cut(histogram.clone(), max_colors);
+    if let QuantizeMethod::KMeans { iterations } = method {
+        k_means(&histogram, &mut colors, iterations);
+    }
+    let palette = Palette::from_rgba8(&colors);
+    let indices = match dithering {
+        Dithering::None => {
+            let mut cache = HashMap::new();
+            pixels
+                .chunks_exact(4)
+                .map(|pixel| {
+                    let rgba = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                    *cache.entry(rgba).or_insert_with(|| palette.nearest(rgba))
+                })
+                .collect()
+        }
+        Dithering::Ordered => {
+            let spread = 255.0 / (colors.len() as f32).cbrt();
+            pixels
+                .chunks_exact(4)
+                .enumerate()
+                .map(|(index, pixel)| {
+                    let (x, y) = (index % width, index / width);
+                    let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                    let offset = |sample: u8| (sample as f32 + threshold * spread).round().clamp(0.0, 255.0) as u8;
+                    palette.nearest([offset(pixel[0]), offset(pixel[1]), offset(pixel[2]), pixel[3]])
+                })
+                .collect()
+        }
+        Dithering::FloydSteinberg => floyd_steinberg(pixels, width, &palette),
+    };
+    (palette, indices)
+}
+
+fn median_cut(histogram: Vec<([u8; 4], usize)>, max_colors: usize) -> Vec<[u8; 4]> {
+    let mut boxes = vec![histogram];
+    while boxes.len() < max_colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| (index, longest_side(colors)))
+            .max_by_key(|(_, (_, range))| *range);
+        let Some((index, (channel, _))) = widest else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(rgba, _)| rgba[channel]);
+        let half = colors.iter().map(|(_, count)| count).sum::<usize>() / 2;
+        let mut count = 0;
+        let median = colors
+            .iter()
+            .position(|(_, c)| {
+                count += c;
+                count > half
+            })
+            .unwrap_or(0)
+            .clamp(1, colors.len() - 1);
+        let upper = colors.split_off(median);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|colors| average(colors.iter().map(|(rgba, count)| (*rgba, *count)))).collect()
+}
+
+fn longest_side(colors: &[([u8; 4], usize)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let min = colors.iter().map(|(rgba, _)| rgba[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(rgba, _)| rgba[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(colors: impl Iterator<Item = ([u8; 4], usize)>) -> [u8; 4] {
+    let mut sum = [0usize; 4];
+    let mut total = 0;
+    for (rgba, count) in colors {
+        for channel in 0..4 {
+            sum[channel] += rgba[channel] as usize * count;
+        }
+        total += count;
+    }
+    sum.map(|value| (value + total / 2).checked_div(total).unwrap_or(0) as u8)
+}
+
+fn k_means(histogram: &[([u8; 4], usize)], centers: &mut [[u8; 4]], iterations: usize) {
+    for _ in 0..iterations {
+        let palette = Palette::from_rgba8(centers);
+        let mut clusters = vec![vec![]; centers.len()];
+        for (rgba, count) in histogram {
+            clusters[palette.nearest(*rgba)].push((*rgba, *count));
+        }
+        let mut moved = false;
+        for (center, cluster) in centers.iter_mut().zip(clusters) {
+            if cluster.is_empty() {
+                continue;
+            }
+            let updated = average(cluster.into_iter());
+            moved |= updated != *center;
+            *center = updated;
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+fn floyd_steinberg(pixels: &[u8], width: usize, palette: &Palette) -> Vec<usize> {
+    let mut errors: Vec<[f32; 4]> = vec![[0.0; 4]; pixels.len() / 4];
+    let height = errors.len() / width.max(1);
+    let mut indices = Vec::with_capacity(errors.len());
+    for (index, pixel) in pixels.chunks_exact(4).enumerate() {
+        let (x, y) = (index % width, index / width);
+        let mut rgba = [0u8; 4];
+        for channel in 0..4 {
+            rgba[channel] = (pixel[channel] as f32 + errors[index][channel]).round().clamp(0.0, 255.0) as u8;
+        }
+        let entry = palette.nearest(rgba);
+        indices.push(entry);
+        let chosen = palette.rgba(entry);
+        let mut diffuse = |dx: isize, dy: usize, weight: f32| {
+            let (nx, ny) = (x as isize + dx, y + dy);
+            if nx >= 0 && (nx as usize) < width && ny < height {
+                let neighbour = &mut errors[ny * width + nx as usize];
+                for channel in 0..4 {
+                    neighbour[channel] += (rgba[channel] as f32 - chosen[channel] as f32) * weight;
+                }
+            }
+        };
+        diffuse(1, 0, 7.0 / 16.0);
+        diffuse(-1, 1, 3.0 / 16.0);
+        diffuse(0, 1, 5.0 / 16.0);
+        diffuse(1, 1, 1.0 / 16.0);
+    }
+    indices
+}
@@ -0,0 +1,103 @@
+/// The function unpacks the samples in the pixel data of a scan line.
+/// Samples narrower than a byte are read from the most significant bits, and 16-bit samples are read in big endian.
+/// The `row` parameter is the pixel data of a scan line without its filter type byte.
+/// The `bit_depth` parameter is the number of bits per sample.
+/// The `count` parameter is the number of samples to read. Missing samples are read as 0.
+pub fn unpack(row: &[u8], bit_depth: u8, count: usize) -> Vec<u16> {
+    (0..count).map(|index| read(row, bit_depth, index)).collect()
+}
+
+/// The function packs samples into the pixel data of a scan line, padding the last byte with zero bits.
+/// The `samples` parameter is the samples to pack.
+/// The `bit_depth` parameter is the number of bits per sample.
+pub fn pack(samples: &[u16], bit_depth: u8) -> Vec<u8> {
+    let mut row = vec![0; (samples.len() * bit_depth as usize).div_ceil(8)];
+    for (index, sample) in samples.iter().enumerate() {
+        write(&mut row, bit_depth, index, *sample);
+    }
+    row
+}
+
+/// The function reads a sample in the pixel data of a scan line. A missing sample is read as 0.
+/// The `row` parameter is the pixel data of a scan line without its filter type byte.
+/// The `bit_depth` parameter is the number of bits per sample.
+/// The `index` parameter is the index of the sample.
+pub fn read(row: &[u8], bit_depth: u8, index: usize) -> u16 {
+    let byte = |index: usize| row.get(index).copied().unwrap_or(0);
+    match bit_depth {
+        16 => u16::from_be_bytes([byte(index * 2), byte(index * 2 + 1)]),
+        8 => byte(index) as u16,
+        _ => {
+            let (byte_index, shift) = position(bit_depth, index);
+            ((byte(byte_index) >> shift) & max_value(bit_depth) as u8) as u16
+        }
+    }
+}
+
+/// The function writes a sample in the pixel data of a scan line, keeping the other samples sharing its byte.
+/// A sample out of the row is ignored.
+/// The `row` parameter is the pixel data of a scan line without its filter type byte.
+/// The `bit_depth` parameter is the number of bits per sample.
+/// The `index` parameter is the index of the sample.
+/// The `value` parameter is the value of the sample, which is truncated to the bit depth.
+pub fn write(row: &mut [u8], bit_depth: u8, index: usize, value: u16) {
+    match bit_depth {
+        16 => {
+            if let Some(bytes) = row.get_mut(index * 2..index * 2 + 2) {
+                bytes.copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        8 => {
+            if let Some(byte) = row.get_mut(index) {
+                *byte = value as u8;
+            }
+        }
+        _ => {
+            let (byte_index, shift) = position(bit_depth, index);
+            let mask = (max_value(bit_depth) as u8) << shift;
+            if let Some(byte) = row.get_mut(byte_index) {
+                *byte = (*byte & !mask) | (((value as u8) << shift) & mask);
+            }
+        }
+    }
+}
+
+/// The function returns the index of the byte holding a sample narrower than a byte and the shift of the sample in the byte.
+fn position(bit_depth: u8, index: usize) -> (usize, usize) {
+    let bit = index * bit_depth as usize;
+    (bit / 8, 8 - bit_depth as usize - bit % 8)
+}
+
+/// The function returns the largest sample value of the given bit depth.
+/// The `bit_depth` parameter is the number of bits per sample.
+pub fn max_value(bit_depth: u8) -> u16 {
+    ((1u32 << bit_depth) - 1) as u16
+}
+
+/// The function scales a sample from one bit depth to another, mapping the largest value to the largest value.
+/// The `sample` parameter is the sample to scale.
+/// The `from` parameter is the bit depth of the sample.
+/// The `to` parameter is the bit depth to scale the sample to.
+pub fn scale(sample: u16, from: u8, to: u8) -> u16 {
+    let (from, to) = (max_value(from) as u32, max_value(to) as u32);
+    ((sample as u32 * to + from / 2) / from) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unpack() {
+        assert_eq!(unpack(&[0b1011_0001], 1, 8), vec![1, 0, 1, 1, 0, 0, 0, 1]);
+        assert_eq!(unpack(&[0b1110_0100], 2, 4), vec![3, 2, 1, 0]);
+        assert_eq!(unpack(&[0x1F, 0xE0], 4, 3), vec![1, 15, 14]);
+        assert_eq!(unpack(&[0x12, 0x34, 0x56], 16, 2), vec![0x1234, 0x5600]);
+        let mut row = [0b1110_0100];
+        write(&mut row, 2, 1, 0);
+        assert_eq!(row, [0b1100_0100]);
+        assert_eq!(scale(1, 1, 8), 255);
+        assert_eq!(scale(0x1234, 16, 8), 0x12);
+        assert_eq!(scale(0xAB, 8, 16), 0xABAB);
+    }
+}
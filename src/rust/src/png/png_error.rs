@@ -37,4 +37,25 @@ pub enum PngError {
     /// A deflate failure occurs.
     #[error("Failed to deflate data.")]
     DeflateFailure,
+    /// The deflate backend does not support the requested compression.
+    #[error("Unsupported compression for the deflate backend.")]
+    UnsupportedCompression,
+    /// An operation which cannot be replayed is found in a recipe.
+    #[error("The operation cannot be replayed.")]
+    UnreplayableOperation,
+    /// An invalid bit depth is found.
+    #[error("Invalid bit depth for the color type.")]
+    InvalidBitDepth,
+    /// The size of the pixel data does not match the size of the image.
+    #[error("The size of the pixel data does not match the size of the image.")]
+    PixelDataSizeMismatch,
+    /// The pixel does not fit the scan line.
+    #[error("The pixel does not fit the scan line.")]
+    InvalidPixel,
+    /// A byte pattern is invalid.
+    #[error("Invalid byte pattern.")]
+    InvalidBytePattern,
+    /// The image has more colors than the palette can hold.
+    #[error("Too many colors for the palette.")]
+    TooManyColors,
 }
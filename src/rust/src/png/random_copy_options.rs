@@ -0,0 +1,77 @@
+use rand::Rng;
+use std::ops::Range;
+
+/// A struct representing the options of copying random scan lines over other random scan lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomCopyOptions {
+    /// The number of the consecutive scan lines copied at once. It is 1 by default.
+    pub lines: u32,
+    /// The range of the indices of the scan lines to copy from. The whole image by default.
+    pub source_band: Option<Range<u32>>,
+    /// The range of the indices of the scan lines to copy to. The whole image by default.
+    pub destination_band: Option<Range<u32>>,
+    /// Whether the filter types are copied with the pixel data. They are copied by default.
+    pub with_filter: bool,
+}
+
+impl Default for RandomCopyOptions {
+    fn default() -> Self {
+        RandomCopyOptions {
+            lines: 1,
+            source_band: None,
+            destination_band: None,
+            with_filter: true,
+        }
+    }
+}
+
+impl RandomCopyOptions {
+    /// The method chooses the scan lines of a copy, and returns the indices of the first scan lines
+    /// to copy from and to, and the number of the scan lines.
+    /// It returns `None` when either band has no scan line in the image.
+    /// The `height` parameter is the number of the scan lines in the image.
+    /// The `rng` parameter is the random generator.
+    pub fn choose(&self, height: u32, rng: &mut impl Rng) -> Option<(u32, u32, u32)> {
+        let clamp = |band: &Option<Range<u32>>| {
+            let band = band.clone().unwrap_or(0..height);
+            band.start.min(height)..band.end.min(height)
+        };
+        let (source_band, destination_band) = (clamp(&self.source_band), clamp(&self.destination_band));
+        if source_band.is_empty() || destination_band.is_empty() {
+            return None;
+        }
+        let lines = self.lines.max(1).min(source_band.len() as u32).min(destination_band.len() as u32);
+        let src = rng.gen_range(source_band.start..=source_band.end - lines);
+        let dst = rng.gen_range(destination_band.start..=destination_band.end - lines);
+        Some((src, dst, lines))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_choose() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let options = RandomCopyOptions {
+            lines: 4,
+            source_band: Some(10..20),
+            destination_band: Some(50..100),
+            with_filter: false,
+        };
+        for _ in 0..100 {
+            let (src, dst, lines) = options.choose(60, &mut rng).expect("The bands should be in the image");
+            assert_eq!(lines, 4);
+            assert!((10..=16).contains(&src));
+            assert!((50..=56).contains(&dst));
+        }
+        let options = RandomCopyOptions {
+            destination_band: Some(60..100),
+            ..options
+        };
+        assert_eq!(options.choose(60, &mut rng), None);
+    }
+}
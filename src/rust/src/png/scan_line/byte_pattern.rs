@@ -0,0 +1,207 @@
+use crate::png::png_error::PngError;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// An enum representing a class of bytes which a byte of a [BytePattern] matches.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteClass {
+    /// Matches any byte.
+    Any,
+    /// Matches the bytes in any of the inclusive ranges, or the bytes in none of them if negated.
+    Set { ranges: Vec<(u8, u8)>, negated: bool },
+}
+
+impl ByteClass {
+    /// The method creates a class matching only the given byte.
+    /// The `byte` parameter is the byte to match.
+    pub fn byte(byte: u8) -> ByteClass {
+        ByteClass::Set {
+            ranges: vec![(byte, byte)],
+            negated: false,
+        }
+    }
+
+    /// The method returns whether the class matches the given byte.
+    /// The `byte` parameter is the byte to test.
+    pub fn matches(&self, byte: u8) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Set { ranges, negated } => ranges.iter().any(|(low, high)| (*low..=*high).contains(&byte)) != *negated,
+        }
+    }
+}
+
+/// A struct representing a pattern of bytes, i.e. a sequence of byte classes, like a regular expression without repetition.
+///
+/// A pattern is parsed from a string with the following syntax:
+///
+/// * `.` matches any byte.
+/// * `\xHH` matches the byte of the hexadecimal value `HH`, e.g. `\x0A`.
+/// * `[...]` matches a byte in the set, e.g. `[\x00-\x1F\xFF]`, and `[^...]` matches a byte not in the set.
+/// * `\` escapes the following character, e.g. `\.` matches a period.
+/// * Any other character matches its UTF-8 bytes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BytePattern {
+    classes: Vec<ByteClass>,
+}
+
+impl BytePattern {
+    /// The method creates a pattern matching the given bytes as they are.
+    /// The `bytes` parameter is the bytes to match.
+    pub fn literal(bytes: &[u8]) -> BytePattern {
+        BytePattern {
+            classes: bytes.iter().map(|byte| ByteClass::byte(*byte)).collect(),
+        }
+    }
+
+    /// The method parses a pattern. See [BytePattern] for the syntax.
+    /// The `pattern` parameter is the string to parse.
+    pub fn parse(pattern: &str) -> anyhow::Result<BytePattern> {
+        let mut bytes = pattern.bytes().peekable();
+        let mut classes = vec![];
+        while let Some(byte) = bytes.next() {
+            let class = match byte {
+                b'.' => ByteClass::Any,
+                b'[' => {
+                    let negated = bytes.next_if_eq(&b'^').is_some();
+                    let mut ranges = vec![];
+                    loop {
+                        let low = match bytes.next() {
+                            Some(b']') if !ranges.is_empty() => break,
+                            Some(b'\\') => Self::parse_escape(&mut bytes)?,
+                            Some(byte) => byte,
+                            None => return Err(PngError::InvalidBytePattern).context(format!("No `]` closes the set in `{}`", pattern)),
+                        };
+                        let high = match bytes.next_if_eq(&b'-') {
+                            Some(_) => match bytes.next() {
+                                Some(b'\\') => Self::parse_escape(&mut bytes)?,
+                                Some(byte) => byte,
+                                None => return Err(PngError::InvalidBytePattern).context(format!("No `]` closes the set in `{}`", pattern)),
+                            },
+                            None => low,
+                        };
+                        if low > high {
+                            return Err(PngError::InvalidBytePattern)
+                                .context(format!("The range from {:#04X} to {:#04X} is reversed", low, high));
+                        }
+                        ranges.push((low, high));
+                    }
+                    ByteClass::Set { ranges, negated }
+                }
+                b'\\' => ByteClass::byte(Self::parse_escape(&mut bytes)?),
+                byte => ByteClass::byte(byte),
+            };
+            classes.push(class);
+        }
+        if classes.is_empty() {
+            return Err(PngError::InvalidBytePattern).context("The pattern is empty");
+        }
+        Ok(BytePattern { classes })
+    }
+
+    /// The method returns the number of the bytes a match of the pattern has.
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// The method returns whether the pattern has no byte class, which matches nothing.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// The method returns the position of the first match at or after the given position, if any.
+    /// The `bytes` parameter is the bytes to search.
+    /// The `from` parameter is the position to start searching at.
+    pub fn find(&self, bytes: &[u8], from: usize) -> Option<usize> {
+        if self.is_empty() || bytes.len() < self.len() {
+            return None;
+        }
+        (from..=bytes.len() - self.len()).find(|start| {
+            self.classes
+                .iter()
+                .zip(&bytes[*start..])
+                .all(|(class, byte)| class.matches(*byte))
+        })
+    }
+
+    /// The method replaces all the non-overlapping matches, from left to right, with the replacement.
+    /// The result is truncated or padded with zeros to the length of the given bytes.
+    /// It returns the number of the replaced matches.
+    /// The `bytes` parameter is the bytes to update.
+    /// The `replacement` parameter is the bytes to replace the matches with, which can differ from the matches in length.
+    pub fn replace(&self, bytes: &mut [u8], replacement: &[u8]) -> usize {
+        let mut replaced = Vec::with_capacity(bytes.len());
+        let (mut position, mut count) = (0, 0);
+        while let Some(start) = self.find(bytes, position) {
+            replaced.extend_from_slice(&bytes[position..start]);
+            replaced.extend_from_slice(replacement);
+            position = start + self.len();
+            count += 1;
+        }
+        if count > 0 {
+            replaced.extend_from_slice(&bytes[position..]);
+            replaced.resize(bytes.len(), 0);
+            bytes.copy_from_slice(&replaced);
+        }
+        count
+    }
+
+    fn parse_escape(bytes: &mut impl Iterator<Item = u8>) -> anyhow::Result<u8> {
+        match bytes.next() {
+            Some(b'x') => {
+                let digits: Vec<u8> = bytes.take(2).collect();
+                std::str::from_utf8(&digits)
+                    .ok()
+                    .filter(|digits| digits.len() == 2)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or(PngError::InvalidBytePattern)
+                    .context("`\\x` should be followed by two hexadecimal digits")
+            }
+            Some(byte) => Ok(byte),
+            None => Err(PngError::InvalidBytePattern).context("The pattern ends with `\\`"),
+        }
+    }
+}
+
+impl FromStr for BytePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BytePattern::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let pattern = BytePattern::parse(r"a.\x0A[^\x00-\x1F\]]\.")?;
+        assert_eq!(pattern.len(), 5);
+        assert_eq!(pattern.find(b"xa\x00\n!.", 0), Some(1));
+        assert_eq!(pattern.find(b"xa\x00\n\x10.", 0), None);
+        assert_eq!(pattern.find(b"xa\x00\n].", 0), None);
+        assert!(BytePattern::parse("").is_err());
+        assert!(BytePattern::parse("[a-").is_err());
+        assert!(BytePattern::parse("[z-a]").is_err());
+        assert!(BytePattern::parse(r"\xG0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace() -> anyhow::Result<()> {
+        let pattern = BytePattern::parse("[0-9]")?;
+        let mut bytes = *b"a1b22c";
+        assert_eq!(pattern.replace(&mut bytes, b"<>"), 3);
+        assert_eq!(&bytes, b"a<>b<>");
+
+        let mut bytes = *b"a1b22c";
+        assert_eq!(pattern.replace(&mut bytes, b""), 3);
+        assert_eq!(&bytes, b"abc\0\0\0");
+        assert_eq!(BytePattern::literal(b"zz").replace(&mut bytes, b"y"), 0);
+        Ok(())
+    }
+}
@@ -16,14 +16,14 @@ pub fn apply(line: &ScanLine, previous: Option<&ScanLine>) {
     scan_rev(line, previous, filter)
 }
 
-fn recon(current: u8, left: u8, previous: u8) -> u8 {
+pub(super) fn recon(current: u8, left: u8, previous: u8) -> u8 {
     let left = left as u16;
     let previous = previous as u16;
     let average = ((left + previous) / 2) % 256;
     add_without_overflow(current, average as u8)
 }
 
-fn filter(current: u8, left: u8, previous: u8) -> u8 {
+pub(super) fn filter(current: u8, left: u8, previous: u8) -> u8 {
     let left = left as u16;
     let previous = previous as u16;
     let average = ((left + previous) / 2) % 256;
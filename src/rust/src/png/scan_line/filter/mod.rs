@@ -31,4 +31,45 @@ pub fn apply(filter_type: FilterType, line: &ScanLine, previous: Option<&ScanLin
         FilterType::Paeth => paeth::apply(line, previous),
         _ => {}
     }
-}
\ No newline at end of file
+}
+
+/// The function removes a filter from the pixel data of a scan line held in a byte array.
+/// The `filter_type` parameter is the type of the filter to remove.
+/// The `line` parameter is the pixel data of the scan line without its filter type byte.
+/// The `previous` parameter is the unfiltered pixel data of the previous scan line.
+/// The `bpp` parameter is the number of bytes per pixel, rounded up to 1.
+pub fn reconstruct(filter_type: FilterType, line: &mut [u8], previous: Option<&[u8]>, bpp: usize) {
+    let above = |index: usize| previous.and_then(|previous| previous.get(index).copied()).unwrap_or(0);
+    for index in 0..line.len() {
+        let left = if index >= bpp { line[index - bpp] } else { 0 };
+        let top_left = if index >= bpp { above(index - bpp) } else { 0 };
+        line[index] = match filter_type {
+            FilterType::None => line[index],
+            FilterType::Sub => byte::add_without_overflow(line[index], left),
+            FilterType::Up => byte::add_without_overflow(line[index], above(index)),
+            FilterType::Average => average::recon(line[index], left, above(index)),
+            FilterType::Paeth => paeth::recon(line[index], left, above(index), top_left),
+        };
+    }
+}
+
+/// The function applies a filter to the unfiltered pixel data of a scan line held in a byte array.
+/// The `filter_type` parameter is the type of the filter to apply.
+/// The `line` parameter is the unfiltered pixel data of the scan line without its filter type byte.
+/// The `previous` parameter is the unfiltered pixel data of the previous scan line.
+/// The `bpp` parameter is the number of bytes per pixel, rounded up to 1.
+pub fn filter_row(filter_type: FilterType, line: &mut [u8], previous: Option<&[u8]>, bpp: usize) {
+    let above = |index: usize| previous.and_then(|previous| previous.get(index).copied()).unwrap_or(0);
+    // The bytes are filtered from the right so that the left bytes are still unfiltered.
+    for index in (0..line.len()).rev() {
+        let left = if index >= bpp { line[index - bpp] } else { 0 };
+        let top_left = if index >= bpp { above(index - bpp) } else { 0 };
+        line[index] = match filter_type {
+            FilterType::None => line[index],
+            FilterType::Sub => byte::sub_without_overflow(line[index], left),
+            FilterType::Up => byte::sub_without_overflow(line[index], above(index)),
+            FilterType::Average => average::filter(line[index], left, above(index)),
+            FilterType::Paeth => paeth::filter(line[index], left, above(index), top_left),
+        };
+    }
+}
@@ -15,12 +15,12 @@ pub fn apply(line: &ScanLine, previous: Option<&ScanLine>) {
     scan_rev(line, previous, filter)
 }
 
-fn recon(current: u8, left: u8, top: u8, top_left: u8) -> u8 {
+pub(super) fn recon(current: u8, left: u8, top: u8, top_left: u8) -> u8 {
     let p = predict(left, top, top_left);
     add_without_overflow(current, p)
 }
 
-fn filter(current: u8, left: u8, top: u8, top_left: u8) -> u8 {
+pub(super) fn filter(current: u8, left: u8, top: u8, top_left: u8) -> u8 {
     let p = predict(left, top, top_left);
     sub_without_overflow(current, p)
 }
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+/// An enum representing the heuristic choosing the filter type of each scan line.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterHeuristic {
+    /// Chooses the filter minimizing the sum of the absolute values of the filtered bytes read as signed integers,
+    /// which is the heuristic recommended by the PNG specification and used by libpng.
+    MinSumOfAbsoluteDifferences,
+    /// Chooses the filter minimizing the Shannon entropy of the filtered bytes.
+    MinEntropy,
+    /// Chooses the filter whose filtered scan line is the smallest when it is compressed alone.
+    SmallestCompressed,
+}
+
+impl FilterHeuristic {
+    /// The method returns the score of a filtered scan line. The filter with the lowest score is chosen.
+    /// The `filtered` parameter is the filtered scan line including its filter type byte.
+    pub fn score(&self, filtered: &[u8]) -> f64 {
+        match self {
+            Self::MinSumOfAbsoluteDifferences => filtered
+                .iter()
+                .skip(1)
+                .map(|byte| (*byte as i8).unsigned_abs() as f64)
+                .sum(),
+            Self::MinEntropy => {
+                let mut counts = [0usize; 256];
+                for byte in filtered.iter().skip(1) {
+                    counts[*byte as usize] += 1;
+                }
+                let total = filtered.len().saturating_sub(1).max(1) as f64;
+                counts
+                    .iter()
+                    .filter(|count| **count > 0)
+                    .map(|count| {
+                        let p = *count as f64 / total;
+                        -p * p.log2()
+                    })
+                    .sum()
+            }
+            Self::SmallestCompressed => {
+                miniz_oxide::deflate::compress_to_vec(filtered, 6).len() as f64
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::png::scan_line::FilterType;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+
+/// An enum representing the plan assigning a filter type to each scan line.
+#[derive(Clone)]
+pub enum FilterPlan {
+    /// Assigns the filter types to the scan lines one by one. The scan lines without a filter type are left as is.
+    Explicit(Vec<FilterType>),
+    /// Repeats the filter types over the scan lines, e.g. `[Sub, Up, Paeth, Sub, Up, Paeth, ...]`.
+    Repeat(Vec<FilterType>),
+    /// Assigns the filter type returned by the function called with the index of each scan line.
+    Custom(Rc<dyn Fn(usize) -> FilterType>),
+}
+
+impl FilterPlan {
+    /// The method creates a plan from a function taking the index of a scan line and returning its filter type.
+    pub fn from_fn<F>(f: F) -> FilterPlan
+    where
+        F: Fn(usize) -> FilterType + 'static,
+    {
+        Self::Custom(Rc::new(f))
+    }
+
+    /// The method returns the filter type assigned to the scan line at the given index.
+    /// It returns `None` when the plan leaves the scan line as is.
+    pub fn filter_type(&self, index: usize) -> Option<FilterType> {
+        match self {
+            Self::Explicit(filters) => filters.get(index).copied(),
+            Self::Repeat(filters) if filters.is_empty() => None,
+            Self::Repeat(filters) => Some(filters[index % filters.len()]),
+            Self::Custom(f) => Some(f(index)),
+        }
+    }
+
+    /// The method returns the number of scan lines the plan covers, given the height of the image.
+    pub(crate) fn lines(&self, height: u32) -> u32 {
+        match self {
+            Self::Explicit(filters) => height.min(filters.len() as u32),
+            Self::Repeat(filters) if filters.is_empty() => 0,
+            _ => height,
+        }
+    }
+}
+
+impl From<Vec<FilterType>> for FilterPlan {
+    fn from(filters: Vec<FilterType>) -> Self {
+        Self::Explicit(filters)
+    }
+}
+
+impl Debug for FilterPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Explicit(filters) => f.debug_tuple("Explicit").field(filters).finish(),
+            Self::Repeat(filters) => f.debug_tuple("Repeat").field(filters).finish(),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::png::png_error::PngError;
+use serde::{Deserialize, Serialize};
 
 /// An enum representing the filter type of a scan line.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FilterType {
     /// No filter.
     None,
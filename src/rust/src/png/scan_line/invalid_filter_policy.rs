@@ -0,0 +1,35 @@
+use crate::png::FilterType;
+use serde::{Deserialize, Serialize};
+
+/// An enum representing how a filter type byte which is not a valid filter type is handled.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidFilterPolicy {
+    /// The byte is left as it is, so strict decoders reject the image.
+    Keep,
+    /// The byte is replaced with the given filter type.
+    Replace(FilterType),
+    /// The byte is replaced with the filter type of its remainder divided by the number of the filter types.
+    Wrap,
+}
+
+impl InvalidFilterPolicy {
+    /// The method returns the filter type byte to write under the policy.
+    /// The `byte` parameter is the filter type byte read from the scan line.
+    pub fn resolve(&self, byte: u8) -> u8 {
+        if FilterType::try_from(byte).is_ok() {
+            return byte;
+        }
+        match self {
+            Self::Keep => byte,
+            Self::Replace(filter_type) => (*filter_type).into(),
+            Self::Wrap => byte % 5,
+        }
+    }
+}
+
+impl Default for InvalidFilterPolicy {
+    fn default() -> Self {
+        Self::Replace(FilterType::None)
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// An enum representing the scan lines an operation is applied to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineScope {
+    /// All the scan lines of the image.
+    #[default]
+    All,
+    /// The given number of the scan lines from the given index.
+    Lines { from: u32, lines: u32 },
+}
+
+impl LineScope {
+    /// The method returns the range of the indices of the scan lines, clamped to the image.
+    /// The `height` parameter is the number of the scan lines in the image.
+    pub fn range(&self, height: u32) -> Range<usize> {
+        match *self {
+            Self::All => 0..height as usize,
+            Self::Lines { from, lines } => {
+                let start = from.min(height);
+                start as usize..from.saturating_add(lines).min(height) as usize
+            }
+        }
+    }
+}
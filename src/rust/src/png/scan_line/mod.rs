@@ -1,14 +1,27 @@
-use crate::png::{ColorType, SharedDecodedData};
+use crate::png::{ColorType, Pixel, SharedDecodedData};
+pub use byte_pattern::{ByteClass, BytePattern};
+pub use filter_heuristic::FilterHeuristic;
+pub use filter_plan::FilterPlan;
 pub use filter_type::FilterType;
+pub use invalid_filter_policy::InvalidFilterPolicy;
+pub use line_scope::LineScope;
 pub use memory_range::MemoryRange;
+pub use reconstructed_view::ReconstructedView;
 use std::fmt::Debug;
 use std::io::{Read, Write};
 use std::ops::{Index, IndexMut, Range};
 use thiserror::Error;
 
+mod byte_pattern;
 mod filter;
+pub(crate) use filter::filter_row;
+mod filter_heuristic;
+mod filter_plan;
 mod filter_type;
+mod invalid_filter_policy;
+mod line_scope;
 mod memory_range;
+mod reconstructed_view;
 
 /// A type alias for a range of `usize`.
 pub type UsizeRange = Range<usize>;
@@ -54,7 +67,7 @@ impl ScanLine {
             ColorType::GrayScaleAlpha => std::cmp::max(bits * 2 / 8, 1) as usize,
             ColorType::TrueColor => std::cmp::max(bits * 3 / 8, 1) as usize,
             ColorType::TrueColorAlpha => std::cmp::max(bits * 4 / 8, 1) as usize,
-            ColorType::IndexColor => (bits / 8) as usize,
+            ColorType::IndexColor => std::cmp::max(bits / 8, 1) as usize,
         }
     }
 
@@ -66,6 +79,21 @@ impl ScanLine {
         self.set_filter_type(filter_type);
     }
 
+    /// The method returns the bytes of the scan line filtered with the given filter type, including the filter type byte,
+    /// without updating the scan line.
+    /// The scan line and the previous one are expected to be unfiltered.
+    /// The `filter_type` parameter is the type of the filter to apply.
+    /// The `previous` parameter is the previous scan line.
+    pub fn filtered(&mut self, filter_type: FilterType, previous: Option<&ScanLine>) -> Vec<u8> {
+        let original = self.decoded_data.borrow()[self.range.clone()].to_vec();
+        let current = self.filter_type;
+        self.apply_filter(filter_type, previous);
+        let filtered = self.decoded_data.borrow()[self.range.clone()].to_vec();
+        self.decoded_data.borrow_mut()[self.range.clone()].copy_from_slice(&original);
+        self.filter_type = current;
+        filtered
+    }
+
     /// The method removes the filter from the scan line.
     /// The `other` parameter is the previous scan line.
     pub fn remove_filter(&mut self, other: Option<&ScanLine>) {
@@ -79,6 +107,7 @@ impl ScanLine {
     }
 
     /// This method updates the filter method of the scan line with the specified one.
+    /// Only the filter type byte is overwritten, so the pixel data is decoded with the new filter, i.e. the filter is grafted.
     pub fn set_filter_type(&mut self, filter_type: FilterType) {
         self.filter_type = filter_type;
         self.decoded_data.borrow_mut()[self.range.start] = filter_type.into();
@@ -89,6 +118,16 @@ impl ScanLine {
         self.range.len() - 1
     }
 
+    /// The method replaces all the non-overlapping matches of the pattern in the pixel data with the replacement,
+    /// shifting the rest of the pixel data. The pixel data is truncated or padded with zeros to the size of the scan line.
+    /// It returns the number of the replaced matches.
+    /// The `pattern` parameter is the pattern to search.
+    /// The `replacement` parameter is the bytes to replace the matches with.
+    pub fn replace_bytes(&mut self, pattern: &BytePattern, replacement: &[u8]) -> usize {
+        let pixel_data_range = self.pixel_data_range();
+        pattern.replace(&mut self.decoded_data.borrow_mut()[pixel_data_range], replacement)
+    }
+
     /// This method returns the color type of the scan line.
     pub fn color_type(&self) -> ColorType {
         self.color_type
@@ -110,6 +149,27 @@ impl ScanLine {
         }
     }
 
+    /// The method returns the pixel at the given position, or `None` when it is out of the scan line.
+    /// The samples are the bytes stored in the scan line, so they are raw filtered values unless the filter type is None.
+    /// Note that the padding bits at the end of a scan line of pixels narrower than a byte are read as a pixel.
+    /// The `x` parameter is the index of the pixel.
+    pub fn get_pixel(&self, x: usize) -> Option<Pixel> {
+        let data = self.decoded_data.borrow();
+        Pixel::read(&data[self.pixel_data_range()], x, self.color_type, self.bit_depth)
+    }
+
+    /// The method updates the pixel at the given position.
+    /// The samples are written as they are, so they are read as raw filtered values unless the filter type is None,
+    /// and a decoder reconstructs different colors from them.
+    /// It fails when the pixel does not match the color type and the bit depth of the scan line, or it is out of the scan line.
+    /// The `x` parameter is the index of the pixel.
+    /// The `pixel` parameter is the pixel to write.
+    pub fn set_pixel(&mut self, x: usize, pixel: Pixel) -> anyhow::Result<()> {
+        let range = self.pixel_data_range();
+        let mut data = self.decoded_data.borrow_mut();
+        pixel.write(&mut data[range], x, self.color_type, self.bit_depth)
+    }
+
     /// The method updates a value of the pixel specified by the index with the given value.
     pub fn update(&self, index: usize, value: u8) {
         let pixel_data_range = self.pixel_data_range();
@@ -221,6 +281,20 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bytes_per_pixel() {
+        let target = TestTarget::new();
+        let scan_line = |color_type, bit_depth| {
+            ScanLine::new(FilterType::None, target.buffer.clone(), target.usize_range(), color_type, bit_depth)
+        };
+        // The filters work on whole bytes, so pixels smaller than a byte count as one byte.
+        for bit_depth in [1, 2, 4, 8] {
+            assert_eq!(scan_line(ColorType::IndexColor, bit_depth).bytes_per_pixel(), 1);
+            assert_eq!(scan_line(ColorType::GrayScale, bit_depth).bytes_per_pixel(), 1);
+        }
+        assert_eq!(scan_line(ColorType::TrueColor, 16).bytes_per_pixel(), 6);
+    }
+
     mod index {
         use crate::png::scan_line::test::TestTarget;
 
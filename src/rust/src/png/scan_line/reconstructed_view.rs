@@ -0,0 +1,101 @@
+use crate::png::scan_line::filter;
+use crate::png::{ColorType, FilterType, Pixel, SharedDecodedData};
+use std::ops::Range;
+
+/// A struct representing a read-only view of scan lines, showing the pixel values a decoder reconstructs from them.
+/// The filters are removed on the fly from copies of the scan lines, so the image data is never modified.
+/// A scan line is reconstructed from the reconstruction of the previous one, which is traced back
+/// to the nearest scan line filtered without the previous one, i.e. with the None or the Sub filter.
+/// Invalid filter type bytes are regarded as no filter.
+pub struct ReconstructedView {
+    decoded_data: SharedDecodedData,
+    lines: Range<usize>,
+    scan_line_width: usize,
+    color_type: ColorType,
+    bit_depth: u8,
+}
+
+impl ReconstructedView {
+    /// The method creates a view of the scan lines in the given range.
+    /// The `decoded_data` parameter is the decoded data of the PNG image.
+    /// The `lines` parameter is the range of the indices of the scan lines to view.
+    /// The `scan_line_width` parameter is the width of a scan line in bytes, including its filter type byte.
+    /// The `color_type` parameter is the color type of the PNG image.
+    /// The `bit_depth` parameter is the bit depth of the PNG image.
+    pub fn new(
+        decoded_data: SharedDecodedData,
+        lines: Range<usize>,
+        scan_line_width: usize,
+        color_type: ColorType,
+        bit_depth: u8,
+    ) -> ReconstructedView {
+        let height = decoded_data.borrow().len() / scan_line_width;
+        let lines = lines.start.min(height)..lines.end.min(height);
+        ReconstructedView {
+            decoded_data,
+            lines,
+            scan_line_width,
+            color_type,
+            bit_depth,
+        }
+    }
+
+    /// The method returns the range of the indices of the scan lines in the view.
+    pub fn lines(&self) -> Range<usize> {
+        self.lines.clone()
+    }
+
+    /// The method returns the reconstructed pixel data of a scan line without its filter type byte,
+    /// or `None` when the scan line is out of the view.
+    /// The `y` parameter is the index of the scan line in the image.
+    pub fn line(&self, y: usize) -> Option<Vec<u8>> {
+        if !self.lines.contains(&y) {
+            return None;
+        }
+        Some(self.trace(y))
+    }
+
+    /// The method returns the reconstructed pixel at the given position,
+    /// or `None` when the scan line is out of the view or the pixel is out of the scan line.
+    /// The `x` parameter is the index of the pixel.
+    /// The `y` parameter is the index of the scan line in the image.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Pixel> {
+        Pixel::read(&self.line(y)?, x, self.color_type, self.bit_depth)
+    }
+
+    /// The method returns an iterator over the reconstructed pixel data of the scan lines in the view.
+    /// Iterating reconstructs each scan line only once.
+    pub fn iter(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let mut previous = self.lines.start.checked_sub(1).map(|y| self.trace(y));
+        self.lines.clone().map(move |y| {
+            let line = self.reconstruct(y, previous.as_deref());
+            previous = Some(line.clone());
+            line
+        })
+    }
+
+    fn trace(&self, y: usize) -> Vec<u8> {
+        let start = (0..=y)
+            .rev()
+            .find(|index| !matches!(self.filter_type(*index), FilterType::Up | FilterType::Average | FilterType::Paeth))
+            .unwrap_or(0);
+        let mut previous: Option<Vec<u8>> = None;
+        for index in start..=y {
+            previous = Some(self.reconstruct(index, previous.as_deref()));
+        }
+        previous.unwrap_or_default()
+    }
+
+    fn filter_type(&self, y: usize) -> FilterType {
+        let byte = self.decoded_data.borrow()[y * self.scan_line_width];
+        FilterType::try_from(byte).unwrap_or(FilterType::None)
+    }
+
+    fn reconstruct(&self, y: usize, previous: Option<&[u8]>) -> Vec<u8> {
+        let start = y * self.scan_line_width;
+        let mut line = self.decoded_data.borrow()[start + 1..start + self.scan_line_width].to_vec();
+        let bpp = self.color_type.bit_per_pixel(self.bit_depth).div_ceil(8);
+        filter::reconstruct(self.filter_type(y), &mut line, previous, bpp);
+        line
+    }
+}
@@ -0,0 +1,110 @@
+use crate::png::png_error::PngError;
+
+/// A struct reading bits from a byte array in the order of deflate streams, i.e. from the least significant bit.
+pub struct BitReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// The method creates a reader starting at the given bit position.
+    /// The `buffer` parameter is the byte array to read.
+    /// The `position` parameter is the position of the first bit to read.
+    pub fn new(buffer: &'a [u8], position: usize) -> BitReader<'a> {
+        BitReader { buffer, position }
+    }
+
+    /// The method returns the position of the next bit to read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The method reads a bit.
+    pub fn bit(&mut self) -> Result<u32, PngError> {
+        let byte = self
+            .buffer
+            .get(self.position / 8)
+            .ok_or(PngError::TooShortInput)?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+
+    /// The method reads the given number of bits as an integer whose least significant bit comes first.
+    /// The `count` parameter is the number of bits to read.
+    pub fn bits(&mut self, count: u32) -> Result<u32, PngError> {
+        let mut value = 0;
+        for index in 0..count {
+            value |= self.bit()? << index;
+        }
+        Ok(value)
+    }
+
+    /// The method skips the bits up to the next byte boundary.
+    pub fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+/// A struct writing bits into a byte array in the order of deflate streams.
+#[derive(Default)]
+pub struct BitWriter {
+    buffer: Vec<u8>,
+    length: usize,
+}
+
+impl BitWriter {
+    /// The method writes the given number of bits of the value from its least significant bit.
+    /// The `value` parameter is the value to write.
+    /// The `count` parameter is the number of bits to write.
+    pub fn bits(&mut self, value: u32, count: u32) {
+        for index in 0..count {
+            if self.length.is_multiple_of(8) {
+                self.buffer.push(0);
+            }
+            let bit = ((value >> index) & 1) as u8;
+            let last = self.buffer.len() - 1;
+            self.buffer[last] |= bit << (self.length % 8);
+            self.length += 1;
+        }
+    }
+
+    /// The method writes a Huffman code, whose most significant bit comes first.
+    /// The `code` parameter is the code to write.
+    /// The `length` parameter is the length of the code in bits.
+    pub fn code(&mut self, code: u32, length: u8) {
+        for index in (0..length as u32).rev() {
+            self.bits((code >> index) & 1, 1);
+        }
+    }
+
+    /// The method copies the bits of a range from the given reader.
+    /// The `reader` parameter is a reader positioned at the first bit to copy.
+    /// The `end` parameter is the position where copying stops.
+    pub fn copy(&mut self, reader: &mut BitReader, end: usize) -> Result<(), PngError> {
+        while reader.position() < end {
+            let count = std::cmp::min(end - reader.position(), 16) as u32;
+            let value = reader.bits(count)?;
+            self.bits(value, count);
+        }
+        Ok(())
+    }
+
+    /// The method pads the bits up to the next byte boundary with zeros.
+    pub fn align(&mut self) {
+        self.length = self.buffer.len() * 8;
+    }
+
+    /// The method writes whole bytes. The writer must be aligned to a byte boundary.
+    /// The `bytes` parameter is the bytes to write.
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.align();
+        self.buffer.extend_from_slice(bytes);
+        self.length = self.buffer.len() * 8;
+    }
+
+    /// The method returns the written bytes, padding the last byte with zeros.
+    pub fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
@@ -0,0 +1,131 @@
+use crate::png::png_error::PngError;
+use crate::png::zlib::bit_io::BitReader;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// The maximum length of a Huffman code in deflate streams.
+pub const MAX_CODE_LENGTH: u8 = 15;
+
+/// A struct decoding symbols encoded with a canonical Huffman code.
+pub struct Huffman {
+    counts: [u16; MAX_CODE_LENGTH as usize + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// The method creates a decoder from the code length of each symbol.
+    /// The `lengths` parameter is the list of code lengths indexed by symbols. 0 means the symbol is unused.
+    pub fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; MAX_CODE_LENGTH as usize + 1];
+        for length in lengths.iter() {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = vec![];
+        for length in 1..=MAX_CODE_LENGTH {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, l)| **l == length) {
+                symbols.push(symbol as u16);
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    /// The method reads a symbol from the reader.
+    /// The `reader` parameter is the reader of the deflate stream.
+    pub fn decode(&self, reader: &mut BitReader) -> Result<u16, PngError> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for length in 1..=MAX_CODE_LENGTH as usize {
+            code |= reader.bit()? as usize;
+            let count = self.counts[length] as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(PngError::DeflateFailure)
+    }
+}
+
+/// The function returns the canonical Huffman code of each symbol.
+/// The `lengths` parameter is the list of code lengths indexed by symbols.
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0u32; MAX_CODE_LENGTH as usize + 1];
+    for length in lengths.iter().filter(|l| **l > 0) {
+        counts[*length as usize] += 1;
+    }
+    let mut next = [0u32; MAX_CODE_LENGTH as usize + 1];
+    let mut code = 0;
+    for length in 1..=MAX_CODE_LENGTH as usize {
+        code = (code + counts[length - 1]) << 1;
+        next[length] = code;
+    }
+    lengths
+        .iter()
+        .map(|length| {
+            if *length == 0 {
+                0
+            } else {
+                let code = next[*length as usize];
+                next[*length as usize] += 1;
+                code
+            }
+        })
+        .collect()
+}
+
+/// The function computes the code lengths of a Huffman code no longer than the limit.
+/// At least 2 symbols get codes so that the code is always complete.
+/// The `frequencies` parameter is the number of occurrences of each symbol.
+/// The `limit` parameter is the maximum code length.
+pub fn code_lengths(frequencies: &[u32], limit: u8) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    for symbol in 0..std::cmp::min(frequencies.len(), 2) {
+        if frequencies.iter().filter(|f| **f > 0).count() < 2 && frequencies[symbol] == 0 {
+            frequencies[symbol] = 1;
+        }
+    }
+    loop {
+        let lengths = huffman_lengths(&frequencies);
+        if lengths.iter().all(|length| *length <= limit) {
+            return lengths;
+        }
+        for frequency in frequencies.iter_mut().filter(|f| **f > 0) {
+            *frequency = (*frequency).div_ceil(2);
+        }
+    }
+}
+
+fn huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut parents: Vec<usize> = vec![usize::MAX; frequencies.len()];
+    let mut heap = BinaryHeap::new();
+    for (symbol, frequency) in frequencies.iter().enumerate().filter(|(_, f)| **f > 0) {
+        heap.push(Reverse((*frequency as u64, symbol)));
+    }
+    while heap.len() > 1 {
+        let Reverse((a, left)) = heap.pop().unwrap_or_default();
+        let Reverse((b, right)) = heap.pop().unwrap_or_default();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = node;
+        parents[right] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    (0..frequencies.len())
+        .map(|symbol| {
+            if frequencies[symbol] == 0 {
+                return 0;
+            }
+            let mut depth = 0;
+            let mut node = symbol;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            std::cmp::min(depth, u8::MAX as usize) as u8
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+use crate::png::zlib::token::{Token, MAX_MATCH_DISTANCE, MAX_MATCH_LENGTH, MIN_MATCH_LENGTH};
+
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN: usize = 64;
+const NONE: usize = usize::MAX;
+
+/// The function splits data into LZ77 tokens with a greedy search,
+/// letting back-references reach into the dictionary placed before the data.
+/// The `data` parameter is the data to tokenize.
+/// The `dictionary` parameter is the bytes preceding the data. Only its last 32 KiB are referred.
+pub fn tokenize(data: &[u8], dictionary: &[u8]) -> Vec<Token> {
+    let dictionary = &dictionary[dictionary.len().saturating_sub(MAX_MATCH_DISTANCE as usize)..];
+    let buffer = [dictionary, data].concat();
+    let mut matcher = Matcher::new(&buffer);
+    for position in 0..dictionary.len() {
+        matcher.insert(position);
+    }
+
+    let mut tokens = vec![];
+    let mut position = dictionary.len();
+    while position < buffer.len() {
+        let (length, distance) = matcher.longest_match(position);
+        if length >= MIN_MATCH_LENGTH as usize {
+            tokens.push(Token::Match {
+                length: length as u16,
+                distance: distance as u16,
+            });
+            for index in position..position + length {
+                matcher.insert(index);
+            }
+            position += length;
+        } else {
+            tokens.push(Token::Literal(buffer[position]));
+            matcher.insert(position);
+            position += 1;
+        }
+    }
+    tokens
+}
+
+struct Matcher<'a> {
+    buffer: &'a [u8],
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(buffer: &'a [u8]) -> Matcher<'a> {
+        Matcher {
+            buffer,
+            head: vec![NONE; 1 << HASH_BITS],
+            previous: vec![NONE; buffer.len()],
+        }
+    }
+
+    fn hash(&self, position: usize) -> Option<usize> {
+        let bytes = self.buffer.get(position..position + MIN_MATCH_LENGTH as usize)?;
+        let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        Some((value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize)
+    }
+
+    fn insert(&mut self, position: usize) {
+        if let Some(hash) = self.hash(position) {
+            self.previous[position] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        let Some(hash) = self.hash(position) else {
+            return (0, 0);
+        };
+        let limit = std::cmp::min(MAX_MATCH_LENGTH as usize, self.buffer.len() - position);
+        let mut best = (0, 0);
+        let mut candidate = self.head[hash];
+        for _ in 0..MAX_CHAIN {
+            if candidate == NONE || position - candidate > MAX_MATCH_DISTANCE as usize {
+                break;
+            }
+            let length = self.buffer[candidate..]
+                .iter()
+                .zip(self.buffer[position..position + limit].iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, position - candidate);
+                if length == limit {
+                    break;
+                }
+            }
+            candidate = self.previous[candidate];
+        }
+        best
+    }
+}
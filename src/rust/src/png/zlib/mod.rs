@@ -0,0 +1,569 @@
+use crate::png::png_error::PngError;
+use anyhow::Context;
+use std::ops::Range;
+use bit_io::{BitReader, BitWriter};
+use huffman::{canonical_codes, code_lengths, Huffman, MAX_CODE_LENGTH};
+pub use token::{distance_of_symbol, Token};
+use token::{
+    length_of_symbol, symbol_of_distance, symbol_of_length, MAX_MATCH_DISTANCE,
+    MAX_MATCH_LENGTH, MIN_MATCH_LENGTH,
+};
+
+mod bit_io;
+mod huffman;
+mod lz77;
+mod token;
+
+/// A struct representing the Huffman code lengths defined in the header of a dynamic deflate block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HuffmanTable {
+    /// The code lengths of the literal/length symbols. 0 means the symbol is unused.
+    pub literals: Vec<u8>,
+    /// The code lengths of the distance symbols. 0 means the symbol is unused.
+    pub distances: Vec<u8>,
+}
+
+impl HuffmanTable {
+    /// The method fits the table into what a block header can hold, so that it can be written after being modified freely.
+    /// The code lengths are padded with zeros or truncated to 286 literal/length symbols and 30 distance symbols,
+    /// and clamped to 15 bits.
+    pub fn fit(&mut self) {
+        self.literals.resize(LITERAL_SYMBOLS, 0);
+        self.distances.resize(DISTANCE_SYMBOLS, 0);
+        for length in self.literals.iter_mut().chain(self.distances.iter_mut()) {
+            *length = (*length).min(MAX_CODE_LENGTH);
+        }
+    }
+}
+
+/// An enum representing the type of a deflate block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockType {
+    /// A block storing bytes without compression.
+    Stored,
+    /// A block compressed with the fixed Huffman codes defined in the deflate specification.
+    Fixed,
+    /// A block compressed with the Huffman codes defined in the block header.
+    Dynamic(HuffmanTable),
+}
+
+/// A struct representing a deflate block.
+pub struct Block {
+    /// Whether the block is the last one of the stream.
+    pub is_final: bool,
+    /// The type of the block.
+    pub block_type: BlockType,
+    /// The tokens in the block. The bytes of a stored block are represented as literals.
+    pub tokens: Vec<Token>,
+    payload: Range<usize>,
+}
+
+/// A struct representing a zlib stream parsed into deflate blocks.
+pub struct ZlibStream {
+    /// The zlib header, including the preset dictionary identifier if any.
+    pub header: Vec<u8>,
+    /// The deflate blocks.
+    pub blocks: Vec<Block>,
+    source: Vec<u8>,
+    trailer: Vec<u8>,
+}
+
+impl ZlibStream {
+    /// The method parses a zlib stream.
+    /// The `buffer` parameter is the zlib stream.
+    pub fn parse(buffer: &[u8]) -> anyhow::Result<ZlibStream> {
+        let header = parse_header(buffer)?;
+        let mut reader = BitReader::new(buffer, header.len() * 8);
+        let mut blocks = vec![];
+        loop {
+            let block = parse_block(&mut reader).context("Failed to parse a deflate block.")?;
+            let is_final = block.is_final;
+            blocks.push(block);
+            if is_final {
+                break;
+            }
+        }
+        reader.align();
+        let trailer = buffer.get(reader.position() / 8..).unwrap_or_default().to_vec();
+        Ok(ZlibStream {
+            header,
+            blocks,
+            source: buffer.to_vec(),
+            trailer,
+        })
+    }
+
+    /// The method writes the stream again with the current block headers.
+    /// The compressed payload of each block is copied bit by bit from the parsed stream, ignoring its tokens,
+    /// so a modified Huffman table makes decoders mis-decode the payload.
+    /// The bytes after the last block, i.e. the Adler-32 trailer, are copied as they are.
+    pub fn reemit(&self) -> anyhow::Result<Vec<u8>> {
+        let mut writer = BitWriter::default();
+        writer.bytes(&self.header);
+        for block in self.blocks.iter() {
+            writer.bits(block.is_final as u32, 1);
+            let mut reader = BitReader::new(&self.source, block.payload.start);
+            match &block.block_type {
+                BlockType::Stored => {
+                    writer.bits(0, 2);
+                    writer.align();
+                    let length = block.tokens.len() as u16;
+                    writer.bytes(&length.to_le_bytes());
+                    writer.bytes(&(!length).to_le_bytes());
+                }
+                BlockType::Fixed => writer.bits(1, 2),
+                BlockType::Dynamic(table) => {
+                    writer.bits(2, 2);
+                    let mut table = table.clone();
+                    table.fit();
+                    write_code_lengths(&mut writer, &table.literals, &table.distances);
+                }
+            }
+            writer
+                .copy(&mut reader, block.payload.end)
+                .context("Failed to copy the payload of a deflate block.")?;
+        }
+        writer.bytes(&self.trailer);
+        Ok(writer.finish())
+    }
+
+    /// The method returns all tokens in the stream.
+    pub fn tokens(&self) -> Vec<Token> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.tokens.iter().copied())
+            .collect()
+    }
+}
+
+/// The function encodes tokens into a zlib stream with dynamic Huffman blocks.
+/// The tokens are sanitized before encoding so that any decoder can decode the stream,
+/// and the Adler-32 trailer is computed over the decoded data.
+/// The `header` parameter is the zlib header to write.
+/// The `tokens` parameter is the tokens to encode.
+/// The `dictionary` parameter is the preset dictionary the tokens may refer to. It is empty without a dictionary.
+pub fn encode(header: &[u8], tokens: &mut [Token], dictionary: &[u8]) -> Vec<u8> {
+    sanitize(tokens, dictionary.len());
+    let mut writer = BitWriter::default();
+    writer.bytes(header);
+    let blocks: Vec<&[Token]> = if tokens.is_empty() {
+        vec![&[]]
+    } else {
+        tokens.chunks(TOKENS_PER_BLOCK).collect()
+    };
+    let last = blocks.len() - 1;
+    for (index, block) in blocks.into_iter().enumerate() {
+        write_dynamic_block(&mut writer, block, index == last);
+    }
+    writer.bytes(&adler32(&decode(tokens, dictionary)).to_be_bytes());
+    writer.finish()
+}
+
+/// The function compresses data into a zlib stream against a preset dictionary.
+/// The header has the FDICT flag and the identifier of the dictionary.
+/// The `data` parameter is the data to compress.
+/// The `dictionary` parameter is the preset dictionary.
+pub fn compress_with_dictionary(data: &[u8], dictionary: &[u8]) -> Vec<u8> {
+    let mut header = header_bytes(7, 2, true).to_vec();
+    header.extend_from_slice(&adler32(dictionary).to_be_bytes());
+    let mut tokens = lz77::tokenize(data, dictionary);
+    encode(&header, &mut tokens, dictionary)
+}
+
+/// The function returns the 2 bytes zlib header with a valid FCHECK field.
+/// The `window_size` parameter is the CINFO field, the base-2 logarithm of the window size minus 8.
+/// The `level_hint` parameter is the FLEVEL field.
+/// The `has_dictionary` parameter is the FDICT flag.
+pub fn header_bytes(window_size: u8, level_hint: u8, has_dictionary: bool) -> [u8; 2] {
+    let cmf = ((window_size & 0x0F) << 4) | 8;
+    let flg = ((level_hint & 0x03) << 6) | ((has_dictionary as u8) << 5);
+    let check = (31 - ((cmf as u16) << 8 | flg as u16) % 31) % 31;
+    [cmf, flg | check as u8]
+}
+
+/// The function decodes tokens into bytes.
+/// Matches referring before the beginning of the dictionary copy zeros.
+/// The `tokens` parameter is the tokens to decode.
+/// The `dictionary` parameter is the preset dictionary the tokens may refer to.
+pub fn decode(tokens: &[Token], dictionary: &[u8]) -> Vec<u8> {
+    let mut data = dictionary.to_vec();
+    for token in tokens.iter() {
+        match *token {
+            Token::Literal(byte) => data.push(byte),
+            Token::Match { length, distance } => {
+                for _ in 0..length {
+                    let byte = data
+                        .len()
+                        .checked_sub(distance as usize)
+                        .map(|index| data[index])
+                        .unwrap_or(0);
+                    data.push(byte);
+                }
+            }
+        }
+    }
+    data.split_off(dictionary.len())
+}
+
+/// The function fixes tokens so that every match has a valid length
+/// and refers only to the data already decoded.
+/// The `tokens` parameter is the tokens to fix.
+/// The `history` parameter is the number of bytes available before the tokens, i.e. the length of the dictionary.
+pub fn sanitize(tokens: &mut [Token], history: usize) {
+    let mut position = history;
+    for token in tokens.iter_mut() {
+        if let Token::Match { length, distance } = *token {
+            if position == 0 {
+                *token = Token::Literal(0);
+            } else {
+                let length = length.clamp(MIN_MATCH_LENGTH, MAX_MATCH_LENGTH);
+                let limit = std::cmp::min(position, MAX_MATCH_DISTANCE as usize) as u16;
+                let distance = distance.clamp(1, limit);
+                *token = Token::Match { length, distance };
+            }
+        }
+        position += match *token {
+            Token::Literal(_) => 1,
+            Token::Match { length, .. } => length as usize,
+        };
+    }
+}
+
+/// The function computes the Adler-32 checksum of the data.
+/// The `data` parameter is the data to compute the checksum of.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+const TOKENS_PER_BLOCK: usize = 16384;
+const END_OF_BLOCK: u16 = 256;
+const LITERAL_SYMBOLS: usize = 286;
+const DISTANCE_SYMBOLS: usize = 30;
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn parse_header(buffer: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if buffer.len() < 2 {
+        return Err(PngError::TooShortInput).context("A zlib stream should have a 2 bytes header.");
+    }
+    let has_dictionary = buffer[1] & 0x20 != 0;
+    let length = if has_dictionary { 6 } else { 2 };
+    let header = buffer
+        .get(..length)
+        .ok_or(PngError::TooShortInput)
+        .context("The preset dictionary identifier is missing.")?;
+    Ok(header.to_vec())
+}
+
+fn parse_block(reader: &mut BitReader) -> Result<Block, PngError> {
+    let is_final = reader.bit()? == 1;
+    let (block_type, start, tokens) = match reader.bits(2)? {
+        0 => {
+            reader.align();
+            let length = reader.bits(16)?;
+            let _ = reader.bits(16)?;
+            let start = reader.position();
+            let tokens = (0..length)
+                .map(|_| reader.bits(8).map(|byte| Token::Literal(byte as u8)))
+                .collect::<Result<_, _>>()?;
+            (BlockType::Stored, start, tokens)
+        }
+        1 => {
+            let start = reader.position();
+            let (literals, distances) = fixed_code_lengths();
+            (BlockType::Fixed, start, parse_tokens(reader, &literals, &distances)?)
+        }
+        2 => {
+            let (literals, distances) = parse_code_lengths(reader)?;
+            let start = reader.position();
+            let tokens = parse_tokens(reader, &literals, &distances)?;
+            (BlockType::Dynamic(HuffmanTable { literals, distances }), start, tokens)
+        }
+        _ => return Err(PngError::DeflateFailure),
+    };
+    Ok(Block {
+        is_final,
+        block_type,
+        tokens,
+        payload: start..reader.position(),
+    })
+}
+
+fn fixed_code_lengths() -> (Vec<u8>, Vec<u8>) {
+    let literals = (0..288)
+        .map(|symbol| match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        })
+        .collect();
+    (literals, vec![5; 30])
+}
+
+fn parse_code_lengths(reader: &mut BitReader) -> Result<(Vec<u8>, Vec<u8>), PngError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_length_lengths[*symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_length_lengths);
+
+    let mut lengths: Vec<u8> = vec![];
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or(PngError::DeflateFailure)?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(PngError::DeflateFailure);
+    }
+    let distances = lengths.split_off(literal_count);
+    Ok((lengths, distances))
+}
+
+fn parse_tokens(reader: &mut BitReader, literals: &[u8], distances: &[u8]) -> Result<Vec<Token>, PngError> {
+    let literal_code = Huffman::new(literals);
+    let distance_code = Huffman::new(distances);
+    let mut tokens = vec![];
+    loop {
+        let symbol = literal_code.decode(reader)?;
+        if symbol < END_OF_BLOCK {
+            tokens.push(Token::Literal(symbol as u8));
+            continue;
+        } else if symbol == END_OF_BLOCK {
+            return Ok(tokens);
+        }
+        let (base, extra) = length_of_symbol(symbol).ok_or(PngError::DeflateFailure)?;
+        let length = base + reader.bits(extra as u32)? as u16;
+        let symbol = distance_code.decode(reader)?;
+        let (base, extra) = distance_of_symbol(symbol).ok_or(PngError::DeflateFailure)?;
+        let distance = base + reader.bits(extra as u32)? as u16;
+        tokens.push(Token::Match { length, distance });
+    }
+}
+
+fn write_dynamic_block(writer: &mut BitWriter, tokens: &[Token], is_final: bool) {
+    let mut literal_frequencies = [0u32; 286];
+    let mut distance_frequencies = [0u32; 30];
+    literal_frequencies[END_OF_BLOCK as usize] = 1;
+    for token in tokens.iter() {
+        match *token {
+            Token::Literal(byte) => literal_frequencies[byte as usize] += 1,
+            Token::Match { length, distance } => {
+                literal_frequencies[symbol_of_length(length).0 as usize] += 1;
+                distance_frequencies[symbol_of_distance(distance).0 as usize] += 1;
+            }
+        }
+    }
+    let literals = code_lengths(&literal_frequencies, MAX_CODE_LENGTH);
+    let distances = code_lengths(&distance_frequencies, MAX_CODE_LENGTH);
+
+    writer.bits(is_final as u32, 1);
+    writer.bits(2, 2);
+    write_code_lengths(writer, &literals, &distances);
+    write_tokens(writer, tokens, &literals, &distances);
+}
+
+fn write_code_lengths(writer: &mut BitWriter, literals: &[u8], distances: &[u8]) {
+    let literal_count = std::cmp::max(last_used(literals), 257);
+    let distance_count = std::cmp::max(last_used(distances), 1);
+    let mut lengths = literals[..literal_count].to_vec();
+    lengths.extend_from_slice(&distances[..distance_count]);
+
+    let symbols = run_length_encode(&lengths);
+    let mut frequencies = [0u32; 19];
+    for (symbol, _, _) in symbols.iter() {
+        frequencies[*symbol as usize] += 1;
+    }
+    let code_length_lengths = code_lengths(&frequencies, 7);
+    let code_length_codes = canonical_codes(&code_length_lengths);
+    let ordered: Vec<u8> = CODE_LENGTH_ORDER
+        .iter()
+        .map(|symbol| code_length_lengths[*symbol])
+        .collect();
+    let code_length_count = std::cmp::max(last_used(&ordered), 4);
+
+    writer.bits((literal_count - 257) as u32, 5);
+    writer.bits((distance_count - 1) as u32, 5);
+    writer.bits((code_length_count - 4) as u32, 4);
+    for length in ordered.iter().take(code_length_count) {
+        writer.bits(*length as u32, 3);
+    }
+    for (symbol, extra, extra_bits) in symbols.into_iter() {
+        let symbol = symbol as usize;
+        writer.code(code_length_codes[symbol], code_length_lengths[symbol]);
+        writer.bits(extra, extra_bits);
+    }
+}
+
+fn write_tokens(writer: &mut BitWriter, tokens: &[Token], literals: &[u8], distances: &[u8]) {
+    let literal_codes = canonical_codes(literals);
+    let distance_codes = canonical_codes(distances);
+    for token in tokens.iter() {
+        match *token {
+            Token::Literal(byte) => {
+                writer.code(literal_codes[byte as usize], literals[byte as usize]);
+            }
+            Token::Match { length, distance } => {
+                let (symbol, extra_bits, extra) = symbol_of_length(length);
+                let symbol = symbol as usize;
+                writer.code(literal_codes[symbol], literals[symbol]);
+                writer.bits(extra as u32, extra_bits as u32);
+                let (symbol, extra_bits, extra) = symbol_of_distance(distance);
+                let symbol = symbol as usize;
+                writer.code(distance_codes[symbol], distances[symbol]);
+                writer.bits(extra as u32, extra_bits as u32);
+            }
+        }
+    }
+    let end = END_OF_BLOCK as usize;
+    writer.code(literal_codes[end], literals[end]);
+}
+
+fn last_used(lengths: &[u8]) -> usize {
+    lengths.iter().rposition(|length| *length > 0).map_or(0, |index| index + 1)
+}
+
+fn run_length_encode(lengths: &[u8]) -> Vec<(u8, u32, u32)> {
+    let mut symbols = vec![];
+    let mut index = 0;
+    while index < lengths.len() {
+        let value = lengths[index];
+        let run = lengths[index..].iter().take_while(|length| **length == value).count();
+        let mut rest = run;
+        if value == 0 {
+            while rest >= 11 {
+                let count = std::cmp::min(rest, 138);
+                symbols.push((18, (count - 11) as u32, 7));
+                rest -= count;
+            }
+            if rest >= 3 {
+                symbols.push((17, (rest - 3) as u32, 3));
+                rest = 0;
+            }
+        } else {
+            symbols.push((value, 0, 0));
+            rest -= 1;
+            while rest >= 3 {
+                let count = std::cmp::min(rest, 6);
+                symbols.push((16, (count - 3) as u32, 2));
+                rest -= count;
+            }
+        }
+        symbols.extend(std::iter::repeat_n((value, 0, 0), rest));
+        index += run;
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode() -> anyhow::Result<()> {
+        let data: Vec<u8> = (0..100000u32).map(|i| ((i / 7) % 251) as u8 ^ (i % 3) as u8).collect();
+        for stream in [
+            fdeflate::compress_to_vec(&data),
+            miniz_oxide::deflate::compress_to_vec_zlib(&data, 9),
+            miniz_oxide::deflate::compress_to_vec_zlib(&data, 0),
+        ] {
+            let parsed = ZlibStream::parse(&stream)?;
+            let mut tokens = parsed.tokens();
+            assert_eq!(data, decode(&tokens, &[]));
+
+            let encoded = encode(&parsed.header, &mut tokens, &[]);
+            assert_eq!(data, fdeflate::decompress_to_vec(&encoded).expect("The stream should be valid"));
+
+            let reemitted = parsed.reemit()?;
+            assert_eq!(data, fdeflate::decompress_to_vec(&reemitted).expect("The stream should be valid"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reemit_with_modified_table() -> anyhow::Result<()> {
+        let data: Vec<u8> = (0..100000u32).map(|i| ((i / 7) % 251) as u8 ^ (i % 3) as u8).collect();
+        let stream = miniz_oxide::deflate::compress_to_vec_zlib(&data, 9);
+        let mut parsed = ZlibStream::parse(&stream)?;
+        for block in parsed.blocks.iter_mut() {
+            if let BlockType::Dynamic(table) = &mut block.block_type {
+                table.literals[..256].reverse();
+            }
+        }
+        let reemitted = parsed.reemit()?;
+        assert_eq!(stream[stream.len() - 4..], reemitted[reemitted.len() - 4..]);
+        assert_ne!(Some(data), fdeflate::decompress_to_vec(&reemitted).ok());
+
+        // Tables of any size are fitted into the block header.
+        for block in parsed.blocks.iter_mut() {
+            if let BlockType::Dynamic(table) = &mut block.block_type {
+                table.literals.truncate(100);
+                table.distances.resize(40, 20);
+            }
+        }
+        let reemitted = parsed.reemit()?;
+        assert_eq!(stream[stream.len() - 4..], reemitted[reemitted.len() - 4..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_with_dictionary() -> anyhow::Result<()> {
+        let dictionary: Vec<u8> = (0..50000u32).map(|i| (i % 253) as u8).collect();
+        let data: Vec<u8> = (0..20000u32).map(|i| ((i + 17) % 253) as u8 ^ (i % 5 == 0) as u8).collect();
+        let stream = compress_with_dictionary(&data, &dictionary);
+        assert_eq!(stream[1] & 0x20, 0x20);
+        assert_eq!(((stream[0] as u16) << 8 | stream[1] as u16) % 31, 0);
+        assert_eq!(stream[2..6], adler32(&dictionary).to_be_bytes());
+
+        let parsed = ZlibStream::parse(&stream)?;
+        let tokens = parsed.tokens();
+        assert_eq!(data, decode(&tokens, &dictionary));
+        assert!(tokens.len() < data.len() / 10);
+        assert_eq!(stream[stream.len() - 4..], adler32(&data).to_be_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut tokens = vec![
+            Token::Match { length: 3, distance: 1 },
+            Token::Literal(1),
+            Token::Match { length: 1000, distance: 100 },
+            Token::Match { length: 1, distance: 0 },
+        ];
+        sanitize(&mut tokens, 0);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal(0),
+                Token::Literal(1),
+                Token::Match { length: 258, distance: 2 },
+                Token::Match { length: 3, distance: 1 },
+            ]
+        );
+    }
+}
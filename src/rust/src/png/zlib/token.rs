@@ -0,0 +1,64 @@
+/// An enum representing a token of a deflate stream, which is the unit LZ77 compression works on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Token {
+    /// A byte written as it is.
+    Literal(u8),
+    /// A back-reference copying `length` bytes from `distance` bytes before the current position.
+    Match {
+        /// The number of bytes to copy, from 3 to 258.
+        length: u16,
+        /// The distance to the bytes to copy, from 1 to 32768.
+        distance: u16,
+    },
+}
+
+/// The minimum length of a match.
+pub const MIN_MATCH_LENGTH: u16 = 3;
+/// The maximum length of a match.
+pub const MAX_MATCH_LENGTH: u16 = 258;
+/// The maximum distance of a match.
+pub const MAX_MATCH_DISTANCE: u16 = 32768;
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The function returns the base length and the number of extra bits of a length symbol.
+/// The `symbol` parameter is a literal/length symbol from 257 to 285.
+pub fn length_of_symbol(symbol: u16) -> Option<(u16, u8)> {
+    let index = (symbol as usize).checked_sub(257)?;
+    Some((*LENGTH_BASES.get(index)?, LENGTH_EXTRA_BITS[index]))
+}
+
+/// The function returns the base distance and the number of extra bits of a distance symbol.
+/// The `symbol` parameter is a distance symbol from 0 to 29.
+pub fn distance_of_symbol(symbol: u16) -> Option<(u16, u8)> {
+    let index = symbol as usize;
+    Some((*DISTANCE_BASES.get(index)?, DISTANCE_EXTRA_BITS[index]))
+}
+
+/// The function returns the symbol, the number of extra bits and the extra bits value of a match length.
+/// The `length` parameter is a length from 3 to 258.
+pub fn symbol_of_length(length: u16) -> (u16, u8, u16) {
+    let index = LENGTH_BASES.iter().rposition(|base| *base <= length).unwrap_or(0);
+    (257 + index as u16, LENGTH_EXTRA_BITS[index], length - LENGTH_BASES[index])
+}
+
+/// The function returns the symbol, the number of extra bits and the extra bits value of a match distance.
+/// The `distance` parameter is a distance from 1 to 32768.
+pub fn symbol_of_distance(distance: u16) -> (u16, u8, u16) {
+    let index = DISTANCE_BASES.iter().rposition(|base| *base <= distance).unwrap_or(0);
+    (index as u16, DISTANCE_EXTRA_BITS[index], distance - DISTANCE_BASES[index])
+}
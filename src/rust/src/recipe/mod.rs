@@ -0,0 +1,166 @@
+mod operation;
+
+pub use operation::Operation;
+
+use crate::png::{EncodeOptions, Png};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// A struct representing the recipe of a glitched image, i.e. the operations performed on the original image
+/// and the options used to encode the result.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recipe {
+    /// The operations in the order they were performed.
+    pub operations: Vec<Operation>,
+    /// The options used to encode the result. `None` means the default options.
+    /// The preset dictionary in the options is left out, as it is often as large as the image.
+    pub encode_options: Option<EncodeOptions>,
+    /// The identifier of the preset dictionary left out of the options, i.e. its Adler-32 checksum.
+    /// The dictionary should be given to [crate::PngGlitch::replay_with_dictionary] to replay the recipe.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary_id: Option<u32>,
+}
+
+impl Recipe {
+    /// The keyword of the iTXt chunk which the recipe is embedded in.
+    pub const KEYWORD: &'static str = "pnglitch-recipe";
+
+    /// The method serializes the recipe as a JSON string.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string(self).context("Failed to serialize the recipe")
+    }
+
+    /// The method deserializes a recipe from a JSON string.
+    /// The `json` parameter is the serialized recipe.
+    pub fn from_json(json: &str) -> anyhow::Result<Recipe> {
+        serde_json::from_str(json).context("Failed to deserialize the recipe")
+    }
+
+    /// The method reads the recipe embedded in a PNG file, if any.
+    /// It does not decode the image data, so it reads the recipe of an image whose compressed data is glitched.
+    /// The `buffer` parameter is a byte array of a PNG file.
+    pub fn from_png(buffer: &[u8]) -> anyhow::Result<Option<Recipe>> {
+        Png::read_text(buffer, Self::KEYWORD)?
+            .map(|json| Recipe::from_json(&json))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::png::{CompressedGlitch, FilterHeuristic, FilterPlan, FilterType, RandomCopyOptions, Strategy, TokenGlitch, ZlibHeader};
+    use crate::PngGlitch;
+
+    #[test]
+    fn test_replay() -> anyhow::Result<()> {
+        let original = include_bytes!("../../etc/none.png").to_vec();
+        let mut png_glitch = PngGlitch::new(original.clone())?.with_seed(7);
+        png_glitch.random_copy(5, &RandomCopyOptions::default());
+        png_glitch.apply_filter_plan(&FilterPlan::from_fn(|index| {
+            if index % 2 == 0 { FilterType::Sub } else { FilterType::Paeth }
+        }));
+        png_glitch.transpose(2, 5, 10);
+        png_glitch.remove_filter_from(3, 4);
+        png_glitch.apply_adaptive_filter_from(FilterHeuristic::MinEntropy, 3, 4);
+        let options = EncodeOptions {
+            token_glitches: vec![TokenGlitch::SwapLiterals { times: 10, seed: 1 }],
+            compressed_glitches: vec![CompressedGlitch::RandomReplace { times: 3, seed: 2 }],
+            embed_recipe: true,
+            ..EncodeOptions::default()
+        };
+        let mut glitched = vec![];
+        png_glitch.encode_with_options(&mut glitched, &options)?;
+
+        let recipe = Recipe::from_png(&glitched)?.expect("The recipe should be embedded");
+        assert_eq!(recipe.operations, png_glitch.operation_log());
+        assert_eq!(PngGlitch::replay(original.clone(), &Recipe::from_json(&recipe.to_json()?)?)?, glitched);
+
+        // The same seed gives the same copies.
+        let mut seeded = PngGlitch::new(original.clone())?.with_seed(7);
+        seeded.random_copy(5, &RandomCopyOptions::default());
+        assert_eq!(seeded.operation_log()[..5], recipe.operations[..5]);
+
+        // Encoding an image with a recipe again replaces the recipe instead of adding another one.
+        let options = EncodeOptions {
+            embed_recipe: true,
+            ..EncodeOptions::default()
+        };
+        let mut encoded = vec![];
+        PngGlitch::new(original.clone())?.encode_with_options(&mut encoded, &options)?;
+        let mut reencoded = vec![];
+        PngGlitch::new(encoded.clone())?.encode_with_options(&mut reencoded, &options)?;
+        assert_eq!(reencoded, encoded);
+
+        png_glitch.foreach_scanline(|_| {});
+        let mut buffer = vec![];
+        png_glitch.encode_with_options(&mut buffer, &options)?;
+        let recipe = Recipe::from_png(&buffer)?.expect("The recipe should be embedded");
+        assert!(PngGlitch::replay(original.clone(), &recipe).is_err());
+
+        // Scan lines handed out for modification make the recipe opaque as well.
+        let png_glitch = PngGlitch::new(original.clone())?;
+        png_glitch.scan_lines_from(0, 1)[0].set_filter_type(FilterType::Up);
+        let mut buffer = vec![];
+        png_glitch.encode_with_options(&mut buffer, &options)?;
+        let recipe = Recipe::from_png(&buffer)?.expect("The recipe should be embedded");
+        assert!(PngGlitch::replay(original, &recipe).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_with_dictionary() -> anyhow::Result<()> {
+        let original = include_bytes!("../../etc/none.png").to_vec();
+        let dictionary = PngGlitch::new(include_bytes!("../../etc/paeth.png").to_vec())?.decoded_data();
+        let mut png_glitch = PngGlitch::new(original.clone())?;
+        png_glitch.transpose(2, 5, 10);
+        let options = EncodeOptions {
+            zlib_header: ZlibHeader {
+                dictionary: Some(dictionary.clone()),
+                ..ZlibHeader::default()
+            },
+            embed_recipe: true,
+            ..EncodeOptions::default()
+        };
+        let mut glitched = vec![];
+        png_glitch.encode_with_options(&mut glitched, &options)?;
+
+        // The dictionary is left out of the recipe, which records only its identifier.
+        let recipe = Recipe::from_png(&glitched)?.expect("The recipe should be embedded");
+        assert!(recipe.to_json()?.len() < dictionary.len());
+        assert_eq!(recipe.dictionary_id, options.zlib_header.dictionary_id());
+        assert_eq!(recipe.encode_options.as_ref().and_then(|options| options.zlib_header.dictionary.clone()), None);
+
+        assert!(PngGlitch::replay(original.clone(), &recipe).is_err());
+        assert!(PngGlitch::replay_with_dictionary(original.clone(), &recipe, &dictionary[1..]).is_err());
+        assert_eq!(PngGlitch::replay_with_dictionary(original, &recipe, &dictionary)?, glitched);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stored_offset_with_recipe() -> anyhow::Result<()> {
+        let mut png_glitch = PngGlitch::new(include_bytes!("../../etc/sample00.png").to_vec())?;
+        png_glitch.transpose(2, 5, 10);
+        let options = EncodeOptions {
+            texts: vec![("Comment".to_string(), "glitched".to_string())],
+            embed_recipe: true,
+            ..EncodeOptions::new(Strategy::Stored)
+        };
+        let mut encoded = vec![];
+        png_glitch.encode_with_options(&mut encoded, &options)?;
+        assert!(Recipe::from_png(&encoded)?.is_some());
+
+        let height = png_glitch.height();
+        let last = png_glitch.width() as usize * 3 - 1;
+        let offsets = [0, 1, height / 2, height - 1]
+            .into_iter()
+            .flat_map(|line| [0, 1, last].map(|index| (line, index)))
+            .map(|(line, index)| (line, index, png_glitch.stored_offset(line, index, &options)))
+            .collect::<Vec<_>>();
+        for (line, index, offset) in offsets {
+            let scan_line = &png_glitch.scan_lines_from(line, 1)[0];
+            assert_eq!(Some(encoded[offset.expect("The offset should exist")]), scan_line.index(index));
+        }
+        Ok(())
+    }
+}
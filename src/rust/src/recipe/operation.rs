@@ -0,0 +1,58 @@
+use crate::png::{BytePattern, ColorType, Dithering, FilterHeuristic, FilterType, InvalidFilterPolicy, LineScope, Pixel, QuantizeMethod};
+use serde::{Deserialize, Serialize};
+
+/// An enum representing an operation performed by [crate::PngGlitch], with its parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum Operation {
+    /// Transposes the scan lines.
+    Transpose { src: u32, dst: u32, lines: u32 },
+    /// Removes the filter from the scan lines.
+    RemoveFilter { from: u32, lines: u32 },
+    /// Applies the filter to the scan lines.
+    ApplyFilter { filter_type: FilterType, from: u32, lines: u32 },
+    /// Converts the filter of the scan lines, keeping their pixels.
+    ConvertFilter { filter_type: FilterType, from: u32, lines: u32 },
+    /// Overwrites the filter type bytes of the scan lines, keeping their filtered data.
+    GraftFilter { filter_type: FilterType, from: u32, lines: u32 },
+    /// Applies the filter chosen by the heuristic to each scan line.
+    ApplyAdaptiveFilter { heuristic: FilterHeuristic, from: u32, lines: u32 },
+    /// Applies the filters to the scan lines one by one from the first scan line.
+    ApplyFilterPlan { filters: Vec<FilterType> },
+    /// Converts the pixels to the color type and the bit depth.
+    Convert { color_type: ColorType, bit_depth: u8 },
+    /// Converts the image to an indexed-color image with a palette of the colors chosen by the method.
+    Quantize { max_colors: usize, method: QuantizeMethod, dithering: Dithering },
+    /// Reinterprets the decoded data under the geometry, the color type and the bit depth.
+    Reinterpret {
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        bit_depth: u8,
+        policy: InvalidFilterPolicy,
+    },
+    /// Copies the scan lines over other scan lines, with or without their filter types.
+    CopyLines { src: u32, dst: u32, lines: u32, with_filter: bool },
+    /// Replaces the matches of the pattern in the scan lines with the replacement.
+    ReplaceBytes { pattern: BytePattern, replacement: Vec<u8>, scope: LineScope },
+    /// Crops the image to the rectangle.
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    /// Pads the image with the pixels of the value.
+    Pad { top: u32, right: u32, bottom: u32, left: u32, fill: Pixel },
+    /// Resizes the image with the nearest-neighbour interpolation.
+    Resize { width: u32, height: u32 },
+    /// Rotates the image by 90 degrees clockwise.
+    Rotate90,
+    /// Rotates the image by 180 degrees.
+    Rotate180,
+    /// Rotates the image by 270 degrees clockwise.
+    Rotate270,
+    /// Flips the image horizontally.
+    FlipHorizontal,
+    /// Flips the image vertically.
+    FlipVertical,
+    /// Runs user code, e.g. a closure given to `foreach_scanline` or code modifying the scan lines returned by `scan_lines`,
+    /// which cannot be recorded nor replayed.
+    /// The `method` field is the name of the method the code was given to.
+    Opaque { method: String },
+}